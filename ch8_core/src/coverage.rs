@@ -0,0 +1,180 @@
+use crate::disassembler::{Access, Instruction};
+use crate::{screenshot, upscale};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+pub const CSV_EXTENSION: &str = "csv";
+///Bytes per row of the map, 64x64 for the 4 KiB memory
+pub const MAP_WIDTH: usize = 64;
+///Number of self-modifications kept, the first ones are the most interesting
+const MAX_SELF_MODIFICATIONS: usize = 256;
+
+///Fetched as part of an instruction
+pub const EXECUTED: u8 = 1 << 0;
+///Read as data by DXYN or FX65
+pub const READ: u8 = 1 << 1;
+///Written by FX33 or FX55
+pub const WRITTEN: u8 = 1 << 2;
+///Written after it was executed
+pub const SELF_MODIFIED: u8 = 1 << 3;
+
+const UNUSED_COLOR: [u8; 3] = [0x20, 0x20, 0x20];
+const SELF_MODIFIED_COLOR: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+///Write to a byte that was executed before
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelfModification {
+	pub frame: u32,
+	///Address of the instruction that wrote the byte
+	pub pc: u16,
+	pub address: u16,
+}
+
+///How each byte of the memory was accessed by the ROM
+#[derive(Clone, Debug)]
+pub struct Coverage {
+	///Access flags of each address
+	pub flags: Vec<u8>,
+	///Writes to executed bytes in the order they happened
+	pub self_modifications: Vec<SelfModification>,
+}
+
+impl Default for Coverage {
+	fn default() -> Self {
+		Self {
+			flags: vec![0; 4096],
+			self_modifications: vec![],
+		}
+	}
+}
+
+impl Coverage {
+	///Record the instruction at `pc` and the memory it accessed starting at `i_register`.
+	///Waiting instructions count as executed, but have not accessed memory yet.
+	pub fn record(
+		&mut self,
+		frame: u32,
+		pc: u16,
+		instruction: Option<Instruction>,
+		waiting: bool,
+		i_register: u16,
+	) {
+		self.set(pc, 2, EXECUTED);
+
+		let (access, length) = match instruction.and_then(|instruction| instruction.memory_access())
+		{
+			Some(access) if !waiting => access,
+			_ => return,
+		};
+
+		match access {
+			Access::Read => self.set(i_register, length, READ),
+			Access::Write => {
+				for offset in 0..length {
+					let address = i_register.wrapping_add(offset) % 4096;
+					let flags = &mut self.flags[address as usize];
+
+					*flags |= WRITTEN;
+					if *flags & EXECUTED != 0 {
+						*flags |= SELF_MODIFIED;
+
+						if self.self_modifications.len() < MAX_SELF_MODIFICATIONS {
+							self.self_modifications
+								.push(SelfModification { frame, pc, address });
+						}
+					}
+				}
+			}
+		}
+	}
+
+	fn set(&mut self, address: u16, length: u16, flag: u8) {
+		for offset in 0..length {
+			self.flags[(address.wrapping_add(offset) % 4096) as usize] |= flag;
+		}
+	}
+
+	///Number of bytes that have `flag`
+	pub fn count(&self, flag: u8) -> usize {
+		self.flags
+			.iter()
+			.filter(|flags| **flags & flag != 0)
+			.count()
+	}
+
+	///Map color of `flags`: executed is green, read is blue and written is red, mixed if several apply.
+	///Self-modified bytes are white.
+	pub fn color(flags: u8) -> [u8; 3] {
+		if flags & SELF_MODIFIED != 0 {
+			return SELF_MODIFIED_COLOR;
+		}
+		if flags == 0 {
+			return UNUSED_COLOR;
+		}
+
+		let channel = |flag: u8| if flags & flag != 0 { 0xC0 } else { 0x00 };
+		[channel(WRITTEN), channel(EXECUTED), channel(READ)]
+	}
+
+	///RGBA pixels of the map, one per byte with [`MAP_WIDTH`] bytes per row
+	pub fn render(&self) -> ([usize; 2], Vec<u8>) {
+		let rgba = self
+			.flags
+			.iter()
+			.flat_map(|flags| {
+				let [r, g, b] = Self::color(*flags);
+				[r, g, b, 0xFF]
+			})
+			.collect();
+
+		([MAP_WIDTH, self.flags.len() / MAP_WIDTH], rgba)
+	}
+
+	///Save the map as a PNG, each byte is a square of `scale` pixels
+	pub fn save_png(&self, path: &Path, scale: usize) -> io::Result<()> {
+		let (size, rgba) = self.render();
+		let (size, rgba) = upscale::nearest(size, &rgba, scale);
+		screenshot::write_png(path, size, &rgba)
+	}
+
+	///Write ranges of bytes with the same flags as CSV, with hex addresses and both ends included.
+	///`kind` is `code` for executed bytes, `data` for bytes only read or written and `unused` otherwise.
+	pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writeln!(writer, "start,end,kind,executed,read,written,self_modified")?;
+
+		let mut start = 0;
+		while start < self.flags.len() {
+			let flags = self.flags[start];
+			let end = self.flags[start..]
+				.iter()
+				.position(|other| *other != flags)
+				.map_or(self.flags.len(), |length| start + length);
+
+			let kind = if flags & EXECUTED != 0 {
+				"code"
+			} else if flags & (READ | WRITTEN) != 0 {
+				"data"
+			} else {
+				"unused"
+			};
+			let flag = |flag: u8| (flags & flag != 0) as u8;
+
+			writeln!(
+				writer,
+				"{:03X},{:03X},{},{},{},{},{}",
+				start,
+				end - 1,
+				kind,
+				flag(EXECUTED),
+				flag(READ),
+				flag(WRITTEN),
+				flag(SELF_MODIFIED)
+			)?;
+
+			start = end;
+		}
+
+		writer.flush()
+	}
+}
@@ -0,0 +1,295 @@
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+use log::{trace, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+//Both files use the format of the chip-8-database (https://github.com/chip-8/chip-8-database)
+//and can be updated by replacing them with newer versions
+const PROGRAMS: &str = include_str!("../database/programs.json");
+const HASHES: &str = include_str!("../database/sha1-hashes.json");
+
+///Information about a known ROM from the bundled ROM database
+#[derive(Clone, Debug, Default)]
+pub struct RomMetadata {
+	pub title: String,
+	pub description: Option<String>,
+	pub release: Option<String>,
+	pub authors: Vec<String>,
+	///chip-8-database id of the platform the ROM was written for, e.g. "superchip"
+	pub platform: Option<String>,
+	pub quirks: Option<Quirks>,
+	pub opcodes_per_frame: Option<u32>,
+	pub palette: Option<Palette>,
+	///Actions and the CHIP-8 keys they are mapped to, e.g. ("left", 0x4)
+	pub keys: Vec<(String, u8)>,
+}
+
+#[derive(Deserialize)]
+struct Program {
+	title: String,
+	description: Option<String>,
+	release: Option<String>,
+	#[serde(default)]
+	authors: Vec<String>,
+	roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+	description: Option<String>,
+	#[serde(default)]
+	platforms: Vec<String>,
+	#[serde(default)]
+	quirky_platforms: HashMap<String, QuirkOverrides>,
+	tickrate: Option<u32>,
+	#[serde(default)]
+	keys: HashMap<String, u8>,
+	colors: Option<Colors>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuirkOverrides {
+	logic: Option<bool>,
+	shift: Option<bool>,
+	memory_increment_by_x: Option<bool>,
+	memory_leave_i_unchanged: Option<bool>,
+	wrap: Option<bool>,
+	jump: Option<bool>,
+	vblank: Option<bool>,
+}
+
+impl QuirkOverrides {
+	fn apply(&self, quirks: &mut Quirks) {
+		let overrides = [
+			(&mut quirks.logic, self.logic),
+			(&mut quirks.shift, self.shift),
+			(
+				&mut quirks.memory_increment_by_x,
+				self.memory_increment_by_x,
+			),
+			(
+				&mut quirks.memory_leave_i_unchanged,
+				self.memory_leave_i_unchanged,
+			),
+			(&mut quirks.wrap, self.wrap),
+			(&mut quirks.jump, self.jump),
+			(&mut quirks.vblank, self.vblank),
+		];
+
+		for (quirk, value) in overrides {
+			if let Some(value) = value {
+				*quirk = value;
+			}
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct Colors {
+	#[serde(default)]
+	pixels: Vec<String>,
+}
+
+///Lowercase hex SHA-1 hash of `rom`, the key of the ROM database
+pub fn hash(rom: &[u8]) -> String {
+	sha1_smol::Sha1::from(rom).digest().to_string()
+}
+
+///Bundled ROM database, parsed on the first lookup
+static DATABASE: LazyLock<Database> = LazyLock::new(|| Database::parse(PROGRAMS, HASHES));
+
+///Look up the ROM with the SHA-1 hash `hash` in the bundled ROM database
+pub fn lookup(hash: &str) -> Option<RomMetadata> {
+	DATABASE.lookup(hash)
+}
+
+#[derive(Default)]
+struct Database {
+	programs: Vec<Program>,
+	///Indices into `programs` by ROM hash
+	hashes: HashMap<String, usize>,
+}
+
+impl Database {
+	///Parse the database files with the contents `programs` and `hashes`.
+	///Parsing errors are logged and result in an empty database.
+	fn parse(programs: &str, hashes: &str) -> Self {
+		let hashes = match serde_json::from_str(hashes) {
+			Ok(hashes) => hashes,
+			Err(e) => {
+				warn!("Error parsing the ROM database hashes: {}", e);
+				return Self::default();
+			}
+		};
+
+		let programs = match serde_json::from_str(programs) {
+			Ok(programs) => programs,
+			Err(e) => {
+				warn!("Error parsing the ROM database programs: {}", e);
+				return Self::default();
+			}
+		};
+
+		Self { programs, hashes }
+	}
+
+	fn lookup(&self, hash: &str) -> Option<RomMetadata> {
+		let index = *self.hashes.get(hash)?;
+
+		let program = match self.programs.get(index) {
+			Some(program) => program,
+			None => {
+				warn!(
+					"ROM database entry {} for hash {} does not exist",
+					index, hash
+				);
+				return None;
+			}
+		};
+
+		let rom = program.roms.get(hash)?;
+		trace!("Found ROM in database: {}", program.title);
+
+		let platform = rom.platforms.first().cloned();
+		let quirks = platform
+			.as_deref()
+			.and_then(Quirks::for_platform)
+			.map(|mut quirks| {
+				if let Some(overrides) = platform
+					.as_ref()
+					.and_then(|platform| rom.quirky_platforms.get(platform))
+				{
+					overrides.apply(&mut quirks);
+				}
+				quirks
+			});
+
+		let palette = rom
+			.colors
+			.as_ref()
+			.and_then(|colors| match colors.pixels.as_slice() {
+				[background, foreground, ..] => Some(Palette {
+					background: parse_color(background)?,
+					foreground: parse_color(foreground)?,
+				}),
+				_ => None,
+			});
+
+		let mut keys: Vec<(String, u8)> = rom
+			.keys
+			.iter()
+			.filter(|(_, key)| **key <= 0xF)
+			.map(|(action, key)| (action.clone(), *key))
+			.collect();
+		keys.sort_by_key(|(_, key)| *key);
+
+		Some(RomMetadata {
+			title: program.title.clone(),
+			description: rom
+				.description
+				.clone()
+				.or_else(|| program.description.clone()),
+			release: program.release.clone(),
+			authors: program.authors.clone(),
+			platform,
+			quirks,
+			opcodes_per_frame: rom.tickrate,
+			palette,
+			keys,
+		})
+	}
+}
+
+///Parse a colour in the form "#RRGGBB"
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+	let hex = color.strip_prefix('#')?;
+	if hex.len() != 6 {
+		return None;
+	}
+
+	let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+	Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bundled_database() {
+		let hashes: HashMap<String, usize> = serde_json::from_str(HASHES).unwrap();
+		let programs: Vec<Program> = serde_json::from_str(PROGRAMS).unwrap();
+
+		for (hash, index) in hashes {
+			let program = &programs[index];
+			assert!(program.roms.contains_key(&hash), "{}", program.title);
+		}
+	}
+
+	#[test]
+	fn lookup_rom() {
+		//Jumps to itself
+		let rom_hash = hash(&[0x12, 0x00]);
+		let programs = format!(
+			r##"[
+				{{"title": "Other", "roms": {{}}}},
+				{{
+					"title": "Loop",
+					"description": "Program description",
+					"authors": ["Someone"],
+					"roms": {{
+						"{}": {{
+							"platforms": ["superchip", "xochip"],
+							"quirkyPlatforms": {{"superchip": {{"vblank": true}}}},
+							"tickrate": 30,
+							"keys": {{"up": 5, "left": 4, "invalid": 16}},
+							"colors": {{"pixels": ["#000000", "#FF8000"]}}
+						}}
+					}}
+				}}
+			]"##,
+			rom_hash
+		);
+		let hashes = format!(r#"{{"{}": 1}}"#, rom_hash);
+
+		let database = Database::parse(&programs, &hashes);
+		let metadata = database.lookup(&rom_hash).unwrap();
+		assert_eq!(metadata.title, "Loop");
+		assert_eq!(metadata.description.as_deref(), Some("Program description"));
+		assert_eq!(metadata.authors, ["Someone"]);
+		assert_eq!(metadata.platform.as_deref(), Some("superchip"));
+		assert_eq!(
+			metadata.quirks,
+			Some(Quirks {
+				vblank: true,
+				..Quirks::SUPER_CHIP
+			})
+		);
+		assert_eq!(metadata.opcodes_per_frame, Some(30));
+		assert_eq!(
+			metadata.palette,
+			Some(Palette {
+				background: [0x00, 0x00, 0x00],
+				foreground: [0xFF, 0x80, 0x00],
+			})
+		);
+		assert_eq!(metadata.keys, [("left".into(), 4), ("up".into(), 5)]);
+
+		assert!(database.lookup(&hash(&[0x00, 0xE0])).is_none());
+		//Indices outside of the programs are ignored
+		let database = Database::parse(&programs, &format!(r#"{{"{}": 2}}"#, rom_hash));
+		assert!(database.lookup(&rom_hash).is_none());
+	}
+
+	#[test]
+	fn colors() {
+		assert_eq!(parse_color("#1A2b3C"), Some([0x1A, 0x2B, 0x3C]));
+		assert_eq!(parse_color("1A2B3C"), None);
+		assert_eq!(parse_color("#1A2B3"), None);
+		assert_eq!(parse_color("#1A2B3G"), None);
+	}
+}
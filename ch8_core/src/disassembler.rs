@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+///How an instruction accesses memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Access {
+	Read,
+	Write,
+}
+
+///CHIP-8 instruction as understood by the core
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+	///0NNN
+	Sys(u16),
+	///00E0
+	Clear,
+	///00EE
+	Return,
+	///1NNN
+	Jump(u16),
+	///2NNN
+	Call(u16),
+	///3XNN
+	SkipEqualImmediate { x: u8, nn: u8 },
+	///4XNN
+	SkipNotEqualImmediate { x: u8, nn: u8 },
+	///5XY0
+	SkipEqual { x: u8, y: u8 },
+	///6XNN
+	LoadImmediate { x: u8, nn: u8 },
+	///7XNN
+	AddImmediate { x: u8, nn: u8 },
+	///8XY0
+	Load { x: u8, y: u8 },
+	///8XY1
+	Or { x: u8, y: u8 },
+	///8XY2
+	And { x: u8, y: u8 },
+	///8XY3
+	Xor { x: u8, y: u8 },
+	///8XY4
+	Add { x: u8, y: u8 },
+	///8XY5
+	Sub { x: u8, y: u8 },
+	///8XY6
+	ShiftRight { x: u8, y: u8 },
+	///8XY7
+	SubReverse { x: u8, y: u8 },
+	///8XYE
+	ShiftLeft { x: u8, y: u8 },
+	///9XY0
+	SkipNotEqual { x: u8, y: u8 },
+	///ANNN
+	LoadI(u16),
+	///BNNN, or BXNN with the jump quirk
+	JumpOffset(u16),
+	///CXNN
+	Random { x: u8, nn: u8 },
+	///DXYN
+	Draw { x: u8, y: u8, n: u8 },
+	///EX9E
+	SkipKeyDown { x: u8 },
+	///EXA1
+	SkipKeyUp { x: u8 },
+	///FX07
+	LoadDelayTimer { x: u8 },
+	///FX0A
+	WaitKey { x: u8 },
+	///FX15
+	SetDelayTimer { x: u8 },
+	///FX18
+	SetSoundTimer { x: u8 },
+	///FX1E
+	AddI { x: u8 },
+	///FX29
+	LoadFont { x: u8 },
+	///FX33
+	StoreBcd { x: u8 },
+	///FX55
+	StoreRegisters { x: u8 },
+	///FX65
+	LoadRegisters { x: u8 },
+}
+
+impl Instruction {
+	///Decode `opcode`, `None` if the core does not understand it
+	pub fn decode(opcode: u16) -> Option<Instruction> {
+		use Instruction::*;
+
+		let x = ((opcode & 0x0F00) >> 8) as u8;
+		let y = ((opcode & 0x00F0) >> 4) as u8;
+		let n = (opcode & 0x000F) as u8;
+		let nn = (opcode & 0x00FF) as u8;
+		let nnn = opcode & 0x0FFF;
+
+		let instruction = match (opcode & 0xF000) >> 12 {
+			0x0 => match opcode {
+				0x00E0 => Clear,
+				0x00EE => Return,
+				_ => Sys(nnn),
+			},
+			0x1 => Jump(nnn),
+			0x2 => Call(nnn),
+			0x3 => SkipEqualImmediate { x, nn },
+			0x4 => SkipNotEqualImmediate { x, nn },
+			0x5 if n == 0x0 => SkipEqual { x, y },
+			0x6 => LoadImmediate { x, nn },
+			0x7 => AddImmediate { x, nn },
+			0x8 => match n {
+				0x0 => Load { x, y },
+				0x1 => Or { x, y },
+				0x2 => And { x, y },
+				0x3 => Xor { x, y },
+				0x4 => Add { x, y },
+				0x5 => Sub { x, y },
+				0x6 => ShiftRight { x, y },
+				0x7 => SubReverse { x, y },
+				0xE => ShiftLeft { x, y },
+				_ => return None,
+			},
+			0x9 if n == 0x0 => SkipNotEqual { x, y },
+			0xA => LoadI(nnn),
+			0xB => JumpOffset(nnn),
+			0xC => Random { x, nn },
+			0xD => Draw { x, y, n },
+			0xE => match nn {
+				0x9E => SkipKeyDown { x },
+				0xA1 => SkipKeyUp { x },
+				_ => return None,
+			},
+			0xF => match nn {
+				0x07 => LoadDelayTimer { x },
+				0x0A => WaitKey { x },
+				0x15 => SetDelayTimer { x },
+				0x18 => SetSoundTimer { x },
+				0x1E => AddI { x },
+				0x29 => LoadFont { x },
+				0x33 => StoreBcd { x },
+				0x55 => StoreRegisters { x },
+				0x65 => LoadRegisters { x },
+				_ => return None,
+			},
+			_ => return None,
+		};
+
+		Some(instruction)
+	}
+
+	///Address a jump or call goes to. For BNNN this is NNN, without the register offset.
+	pub fn target(&self) -> Option<u16> {
+		match *self {
+			Instruction::Jump(address)
+			| Instruction::Call(address)
+			| Instruction::JumpOffset(address) => Some(address),
+			_ => None,
+		}
+	}
+
+	///How many bytes starting at I the instruction reads as data or writes
+	pub fn memory_access(&self) -> Option<(Access, u16)> {
+		match *self {
+			Instruction::Draw { n, .. } => Some((Access::Read, n as u16)),
+			Instruction::LoadRegisters { x } => Some((Access::Read, x as u16 + 1)),
+			Instruction::StoreBcd { .. } => Some((Access::Write, 3)),
+			Instruction::StoreRegisters { x } => Some((Access::Write, x as u16 + 1)),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		use Instruction::*;
+
+		match *self {
+			Sys(nnn) => write!(f, "SYS {:03X}", nnn),
+			Clear => write!(f, "CLS"),
+			Return => write!(f, "RET"),
+			Jump(nnn) => write!(f, "JP {:03X}", nnn),
+			Call(nnn) => write!(f, "CALL {:03X}", nnn),
+			SkipEqualImmediate { x, nn } => write!(f, "SE V{:X}, {:02X}", x, nn),
+			SkipNotEqualImmediate { x, nn } => write!(f, "SNE V{:X}, {:02X}", x, nn),
+			SkipEqual { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+			LoadImmediate { x, nn } => write!(f, "LD V{:X}, {:02X}", x, nn),
+			AddImmediate { x, nn } => write!(f, "ADD V{:X}, {:02X}", x, nn),
+			Load { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+			Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+			And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+			Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+			Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+			Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+			ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+			SubReverse { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+			ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+			SkipNotEqual { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+			LoadI(nnn) => write!(f, "LD I, {:03X}", nnn),
+			JumpOffset(nnn) => write!(f, "JP V0, {:03X}", nnn),
+			Random { x, nn } => write!(f, "RND V{:X}, {:02X}", x, nn),
+			Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {:X}", x, y, n),
+			SkipKeyDown { x } => write!(f, "SKP V{:X}", x),
+			SkipKeyUp { x } => write!(f, "SKNP V{:X}", x),
+			LoadDelayTimer { x } => write!(f, "LD V{:X}, DT", x),
+			WaitKey { x } => write!(f, "LD V{:X}, K", x),
+			SetDelayTimer { x } => write!(f, "LD DT, V{:X}", x),
+			SetSoundTimer { x } => write!(f, "LD ST, V{:X}", x),
+			AddI { x } => write!(f, "ADD I, V{:X}", x),
+			LoadFont { x } => write!(f, "LD F, V{:X}", x),
+			StoreBcd { x } => write!(f, "LD B, V{:X}", x),
+			StoreRegisters { x } => write!(f, "LD [I], V{:X}", x),
+			LoadRegisters { x } => write!(f, "LD V{:X}, [I]", x),
+		}
+	}
+}
+
+///Decoded instruction at an address
+#[derive(Clone, Copy, Debug)]
+pub struct Line {
+	pub address: u16,
+	pub opcode: u16,
+	///`None` for opcodes the core does not understand, which are usually data
+	pub instruction: Option<Instruction>,
+}
+
+impl Line {
+	pub fn new(address: u16, opcode: u16) -> Self {
+		Self {
+			address,
+			opcode,
+			instruction: Instruction::decode(opcode),
+		}
+	}
+}
+
+impl fmt::Display for Line {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match &self.instruction {
+			Some(instruction) => write!(f, "{}", instruction),
+			None => write!(f, "DW {:04X}", self.opcode),
+		}
+	}
+}
+
+///Disassemble `count` instructions starting at `start`.
+///`start` may be odd, for ROMs that jump into the middle of an instruction.
+pub fn disassemble(memory: &[u8], start: u16, count: usize) -> Vec<Line> {
+	(0..count)
+		.map(|i| start as usize + i * 2)
+		.take_while(|address| address + 1 < memory.len())
+		.map(|address| {
+			let opcode = (memory[address] as u16) << 8 | memory[address + 1] as u16;
+			Line::new(address as u16, opcode)
+		})
+		.collect()
+}
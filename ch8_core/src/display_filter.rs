@@ -0,0 +1,118 @@
+use crate::framebuffer::{Framebuffer, PIXEL_OFF};
+use crate::palette::Palette;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+pub const DEFAULT_DECAY: f32 = 0.6;
+
+///Presentation filters against the flicker caused by sprites being erased and redrawn every frame.
+///They only change how published frames are shown, never the emulated framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DisplayFilter {
+	None,
+	///Blend every pixel's on-state over the last frames,
+	///`decay` is the share of brightness a pixel keeps per frame after being turned off
+	Persistence {
+		decay: f32,
+	},
+	///Show a pixel as set if it was set in either of the last two frames
+	MaxOfLastTwo,
+}
+
+impl DisplayFilter {
+	pub const ALL: [DisplayFilter; 3] = [
+		DisplayFilter::None,
+		DisplayFilter::Persistence {
+			decay: DEFAULT_DECAY,
+		},
+		DisplayFilter::MaxOfLastTwo,
+	];
+}
+
+impl fmt::Display for DisplayFilter {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			DisplayFilter::None => write!(f, "None"),
+			DisplayFilter::Persistence { .. } => write!(f, "Phosphor persistence"),
+			DisplayFilter::MaxOfLastTwo => write!(f, "Max of last two frames"),
+		}
+	}
+}
+
+///Applies a [`DisplayFilter`] to a sequence of frames
+#[derive(Clone)]
+pub struct FrameBlender {
+	filter: DisplayFilter,
+	size: [usize; 2],
+	///Brightness of every pixel from 0.0 (unset) to 1.0 (set)
+	intensities: Vec<f32>,
+	previous_frame: Option<Framebuffer>,
+}
+
+impl FrameBlender {
+	pub fn new(filter: DisplayFilter) -> Self {
+		Self {
+			filter,
+			size: [0, 0],
+			intensities: vec![],
+			previous_frame: None,
+		}
+	}
+
+	pub fn filter(&self) -> DisplayFilter {
+		self.filter
+	}
+
+	pub fn set_filter(&mut self, filter: DisplayFilter) {
+		self.filter = filter;
+	}
+
+	///Add the next published frame
+	pub fn push(&mut self, frame: &Framebuffer) {
+		if self.size != frame.get_size() {
+			self.size = frame.get_size();
+			self.intensities = vec![0.0; self.size[0] * self.size[1]];
+			self.previous_frame = None;
+		}
+
+		for (i, (intensity, pixel)) in self
+			.intensities
+			.iter_mut()
+			.zip(frame.get_pixels())
+			.enumerate()
+		{
+			let set = if *pixel != PIXEL_OFF { 1.0 } else { 0.0 };
+
+			*intensity = match self.filter {
+				DisplayFilter::None => set,
+				DisplayFilter::Persistence { decay } => f32::max(set, *intensity * decay),
+				DisplayFilter::MaxOfLastTwo => {
+					let previously_set = match &self.previous_frame {
+						Some(previous_frame) => previous_frame.get_pixels()[i] != PIXEL_OFF,
+						None => false,
+					};
+
+					if previously_set {
+						1.0
+					} else {
+						set
+					}
+				}
+			};
+		}
+
+		self.previous_frame = Some(frame.clone());
+	}
+
+	///Apply `palette` to the blended frame.
+	///Returns the size of the rendered image and its RGBA bytes.
+	pub fn render(&self, palette: &Palette) -> ([usize; 2], Vec<u8>) {
+		let mut buf = Vec::with_capacity(self.intensities.len() * 4);
+		for intensity in &self.intensities {
+			buf.extend_from_slice(&palette.blend(*intensity));
+		}
+
+		(self.size, buf)
+	}
+}
@@ -0,0 +1,74 @@
+use crate::palette::Palette;
+use crate::upscale;
+use std::ops::{Index, IndexMut};
+
+pub const PIXEL_OFF: u8 = 0;
+pub const PIXEL_ON: u8 = 1;
+
+///Logical framebuffer, every pixel stores an index into a [`Palette`].
+///Colours are only applied when the framebuffer is presented.
+#[derive(Clone)]
+pub struct Framebuffer {
+	size: [usize; 2],
+	pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+	pub fn new(size: [usize; 2]) -> Self {
+		Self {
+			size,
+			pixels: vec![PIXEL_OFF; size[0] * size[1]],
+		}
+	}
+
+	///Framebuffer with the palette indices of `pixels`, row by row. `None` if they do not fill `size`.
+	pub fn from_pixels(size: [usize; 2], pixels: Vec<u8>) -> Option<Self> {
+		if pixels.len() != size[0] * size[1] {
+			return None;
+		}
+
+		Some(Self { size, pixels })
+	}
+
+	pub fn get_size(&self) -> [usize; 2] {
+		self.size
+	}
+
+	pub fn get_scaled_size(&self, scale: f32) -> [f32; 2] {
+		[self.size[0] as f32 * scale, self.size[1] as f32 * scale]
+	}
+
+	///Palette indices of all pixels, row by row
+	pub fn get_pixels(&self) -> &[u8] {
+		&self.pixels
+	}
+
+	pub fn clear(&mut self) {
+		self.pixels.fill(PIXEL_OFF);
+	}
+
+	///Apply `palette` and scale every pixel up to a `scale`x`scale` block.
+	///Returns the size of the rendered image and its RGBA bytes.
+	pub fn render(&self, palette: &Palette, scale: usize) -> ([usize; 2], Vec<u8>) {
+		let mut buf = Vec::with_capacity(self.pixels.len() * 4);
+		for pixel in &self.pixels {
+			buf.extend_from_slice(&palette.color(*pixel));
+		}
+
+		upscale::nearest(self.size, &buf, scale)
+	}
+}
+
+impl Index<(usize, usize)> for Framebuffer {
+	type Output = u8;
+
+	fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+		&self.pixels[y * self.size[0] + x]
+	}
+}
+
+impl IndexMut<(usize, usize)> for Framebuffer {
+	fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+		&mut self.pixels[y * self.size[0] + x]
+	}
+}
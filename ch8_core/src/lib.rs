@@ -1,18 +1,57 @@
 mod audio;
-
-//TODO Maybe add wrapper type?
-pub use pixel_buf;
-
-use log::{error, trace, warn};
-use pixel_buf::{PixelBuf, Rgba};
+pub mod coverage;
+pub mod database;
+pub mod disassembler;
+pub mod display_filter;
+pub mod framebuffer;
+pub mod palette;
+pub mod profiler;
+pub mod quirks;
+pub mod report;
+pub mod rom;
+pub mod save_state;
+pub mod screenshot;
+pub mod trace;
+pub mod trace_diff;
+pub mod upscale;
+
+use coverage::Coverage;
+use database::RomMetadata;
+use disassembler::Instruction;
+use framebuffer::{Framebuffer, PIXEL_OFF, PIXEL_ON};
+use log::{error, info, trace, warn};
+use profiler::{Profile, WaitKind};
+use quirks::Quirks;
+use report::CrashReport;
+use save_state::SaveState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
 use std::fmt::{Debug, Formatter};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{fmt, fs, thread};
+use trace::{Touched, TraceRecord, TraceRecorder};
 
 const FPS: f64 = 60.0;
+///Speed multiplier while fast-forwarding
+const FAST_FORWARD_SPEED: f64 = 4.0;
+///Number of frames that can be rewound, 10 seconds at 60 fps
+const REWIND_FRAMES: usize = 600;
+///Number of executed instructions attached to errors
+const HISTORY_LENGTH: usize = 64;
+///Number of applied key events kept for crash reports
+const KEY_HISTORY_LENGTH: usize = 32;
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
+///Address of the built-in hex font, 5 bytes per character
+pub const FONT_ADDRESS: usize = 0;
+pub const FONT_SIZE: usize = 16 * 5;
+///Address ROMs are loaded to
+pub const ROM_ADDRESS: usize = 512;
+///Highest address the program counter can be set to, the opcode at it ends with the last byte of memory
+pub const MAX_PC: u16 = 0xFFE;
+///Highest address that fits in the 12 bits of I
+pub const MAX_I: u16 = 0xFFF;
 
 #[derive(Debug)]
 pub enum Event {
@@ -22,27 +61,95 @@ pub enum Event {
 	ChangeOpcodesPerFrame(u32),
 	Exit,
 	ChangeVolume(f32),
-	KeysDown([bool; 16]),
+	///Key pressed at the given time
+	KeyDown(u8, Instant),
+	///Key released at the given time
+	KeyUp(u8, Instant),
+	ChangeQuirks(Quirks),
+	///Store the machine state in the quick save slot and the save state file of the ROM
+	SaveState,
+	///Restore the quick save slot, or the save state file of the ROM if the slot is empty
+	LoadState,
+	ChangeFastForward(bool),
+	///Run the recorded frames backwards while `true`
+	ChangeRewinding(bool),
+	///Overwrite memory starting at `address`
+	WriteMemory {
+		address: u16,
+		bytes: Vec<u8>,
+	},
+	///Set V`register` to `value`
+	SetRegister {
+		register: u8,
+		value: u8,
+	},
+	SetPc(u16),
+	SetI(u16),
+	SetTimer {
+		timer: Timer,
+		value: u8,
+	},
+	ChangeBreakpoint {
+		address: u16,
+		enabled: bool,
+	},
+	///Record the executed instructions, replacing a running trace
+	StartTrace(TraceRecorder),
+	StopTrace,
+	///Start counting the executed instructions with an empty profile, or stop and discard the profile
+	ChangeProfiling(bool),
+	///Clear the counts of the running profiler
+	ResetProfile,
+	///Forget how the memory was accessed so far
+	ResetCoverage,
+	///Restore the machine state of a crash report, which stops the core with the reported error
+	LoadCrashReport(Box<CrashReport>),
+	///Restore a save state in place of a ROM, quick saves are written to `path`
+	LoadSaveState {
+		save_state: Box<SaveState>,
+		path: PathBuf,
+	},
 }
 
 impl fmt::Display for Event {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-		if let Event::LoadRom(path) = self {
-			write!(f, "LoadRom({})", path.display())
-		} else {
-			write!(f, "{:?}", self)
+		match self {
+			Event::LoadRom(path) => write!(f, "LoadRom({})", path.display()),
+			Event::StartTrace(trace) => write!(f, "StartTrace({})", trace.path().display()),
+			Event::LoadCrashReport(report) => write!(f, "LoadCrashReport({})", report.error),
+			Event::LoadSaveState { path, .. } => write!(f, "LoadSaveState({})", path.display()),
+			_ => write!(f, "{:?}", self),
 		}
 	}
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timer {
+	Delay,
+	Sound,
+}
+
+impl fmt::Display for Timer {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Timer::Delay => write!(f, "DT"),
+			Timer::Sound => write!(f, "ST"),
+		}
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ErrorKind {
 	InvalidOpcode {
 		opcode: u16,
 		address: u16,
+		///Instructions executed before, the newest last
+		history: Vec<TraceRecord>,
 	},
 	InvalidReturn {
 		address: u16,
+		///Instructions executed before, the newest last
+		history: Vec<TraceRecord>,
 	},
 	RomTooLarge {
 		path: PathBuf,
@@ -55,17 +162,41 @@ pub enum ErrorKind {
 	},
 }
 
+impl ErrorKind {
+	///Address of the instruction that caused the error
+	pub fn address(&self) -> Option<u16> {
+		match self {
+			ErrorKind::InvalidOpcode { address, .. } | ErrorKind::InvalidReturn { address, .. } => {
+				Some(*address)
+			}
+			_ => None,
+		}
+	}
+
+	///Instructions executed before the error, the newest last
+	pub fn history(&self) -> &[TraceRecord] {
+		match self {
+			ErrorKind::InvalidOpcode { history, .. } | ErrorKind::InvalidReturn { history, .. } => {
+				history
+			}
+			_ => &[],
+		}
+	}
+}
+
 impl fmt::Display for ErrorKind {
 	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
 		match self {
-			ErrorKind::InvalidOpcode { opcode, address } => {
+			ErrorKind::InvalidOpcode {
+				opcode, address, ..
+			} => {
 				write!(
 					f,
 					"Invalid opcode: '{:#06X}' at PC: '{:#06X}'",
 					opcode, address
 				)
 			}
-			ErrorKind::InvalidReturn { address } => {
+			ErrorKind::InvalidReturn { address, .. } => {
 				write!(f, "Invalid return at PC: '{:#06X}'", address)
 			}
 			ErrorKind::RomTooLarge {
@@ -117,8 +248,11 @@ impl Chip8Core {
 
 #[derive(Clone)]
 pub struct CoreState {
-	pub image: PixelBuf,
+	pub image: Framebuffer,
 	pub current_frame: u32,
+	///Number of images produced by running, rewinding or loading a state. Unlike `current_frame`
+	///it never goes back, and it stays the same when the state is only sent again because of an event.
+	pub frames_produced: u64,
 	pub actual_frame_time: Duration,
 	pub frame_time_with_sleep: Duration,
 	pub fps: f64,
@@ -136,17 +270,38 @@ pub struct CoreState {
 	pub sound_timer: u8,
 	pub rom_name: Option<String>,
 	pub rom_size: Option<usize>,
+	///Lowercase hex SHA-1 hash of the loaded ROM
+	pub rom_hash: Option<String>,
+	///Entry of the loaded ROM in the bundled ROM database
+	pub rom_metadata: Option<RomMetadata>,
 	pub opcodes_per_frame: u32,
+	pub quirks: Quirks,
 	pub exit_requested: bool,
 	pub keys_down: [bool; 16],
-	pub previous_keys_down: [bool; 16],
+	///Keys the ROM checked during the last frame
+	pub keys_polled: [bool; 16],
+	pub fast_forward: bool,
+	pub rewinding: bool,
+	///Whether the quick save slot contains a state
+	pub state_saved: bool,
+	///Addresses that pause the core before the instruction there is executed
+	pub breakpoints: HashSet<u16>,
+	///File the executed instructions are written to
+	pub trace_path: Option<PathBuf>,
+	///Last applied key events, the newest at the back
+	pub key_history: VecDeque<KeyRecord>,
+	///Execution counts, `None` while the profiler is disabled
+	pub profile: Option<Profile>,
+	///How the ROM accessed each byte of `memory`
+	pub coverage: Coverage,
 }
 
 impl CoreState {
-	pub fn new(image: PixelBuf) -> Self {
+	pub fn new(image: Framebuffer) -> Self {
 		Self {
 			image,
 			current_frame: 0,
+			frames_produced: 0,
 			actual_frame_time: Duration::new(0, 0),
 			frame_time_with_sleep: Duration::new(0, 0),
 			fps: 0.0,
@@ -164,14 +319,121 @@ impl CoreState {
 			sound_timer: 0,
 			rom_name: None,
 			rom_size: None,
+			rom_hash: None,
+			rom_metadata: None,
 			opcodes_per_frame: 20,
+			quirks: Quirks::default(),
 			exit_requested: false,
 			keys_down: [false; 16],
-			previous_keys_down: [false; 16],
+			keys_polled: [false; 16],
+			fast_forward: false,
+			rewinding: false,
+			state_saved: false,
+			breakpoints: HashSet::new(),
+			trace_path: None,
+			key_history: VecDeque::with_capacity(KEY_HISTORY_LENGTH),
+			profile: None,
+			coverage: Coverage::default(),
 		}
 	}
 }
 
+///Key press or release applied at the start of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRecord {
+	pub frame: u32,
+	pub key: u8,
+	pub down: bool,
+}
+
+///Key press or release that has not been applied yet
+struct KeyEvent {
+	key: u8,
+	down: bool,
+	time: Instant,
+}
+
+///Executed instruction and the registers after it, kept for error reports
+#[derive(Clone, Copy)]
+struct Executed {
+	frame: u32,
+	pc: u16,
+	opcode: u16,
+	v_registers: [u8; 16],
+	i_register: u16,
+}
+
+impl Executed {
+	///Trace record with the registers that changed since `previous`, the instruction executed before.
+	///Memory accesses are only known while tracing.
+	fn to_record(self, previous: Option<&Executed>) -> TraceRecord {
+		let mut touched = vec![];
+		if let Some(previous) = previous {
+			touched.extend(
+				(0..16)
+					.filter(|register| {
+						self.v_registers[*register] != previous.v_registers[*register]
+					})
+					.map(|register| Touched::Register(register as u8, self.v_registers[register])),
+			);
+			if self.i_register != previous.i_register {
+				touched.push(Touched::I(self.i_register));
+			}
+		}
+
+		TraceRecord {
+			frame: self.frame,
+			pc: self.pc,
+			opcode: self.opcode,
+			v_registers: self.v_registers,
+			i_register: self.i_register,
+			touched,
+		}
+	}
+}
+
+///Machine state that is stored for save states and rewinding
+#[derive(Clone)]
+struct Snapshot {
+	image: Framebuffer,
+	current_frame: u32,
+	memory: [u8; 4096],
+	v_registers: [u8; 16],
+	i_register: u16,
+	program_counter: u16,
+	call_stack: Vec<u16>,
+	delay_timer: u8,
+	sound_timer: u8,
+}
+
+impl Snapshot {
+	fn take(state: &CoreState) -> Self {
+		Self {
+			image: state.image.clone(),
+			current_frame: state.current_frame,
+			memory: state.memory,
+			v_registers: state.v_registers,
+			i_register: state.i_register,
+			program_counter: state.program_counter,
+			call_stack: state.call_stack.clone(),
+			delay_timer: state.delay_timer,
+			sound_timer: state.sound_timer,
+		}
+	}
+
+	fn restore(&self, state: &mut CoreState) {
+		state.image = self.image.clone();
+		state.current_frame = self.current_frame;
+		state.memory = self.memory;
+		state.v_registers = self.v_registers;
+		state.i_register = self.i_register;
+		state.program_counter = self.program_counter;
+		state.call_stack = self.call_stack.clone();
+		state.delay_timer = self.delay_timer;
+		state.sound_timer = self.sound_timer;
+	}
+}
+
 struct Core {
 	state: CoreState,
 	sleep_error_millis: f64,
@@ -179,6 +441,22 @@ struct Core {
 	events: crossbeam_channel::Receiver<Event>,
 	sound_event_sender: crossbeam_channel::Sender<audio::Event>,
 	repaint_frontend_callback: Box<dyn Fn() + Send>,
+	///Quick save slot
+	saved_state: Option<Snapshot>,
+	///File the quick save slot is written to, next to the ROM
+	state_path: Option<PathBuf>,
+	///States at the start of the last frames, the newest at the back
+	rewind_buffer: VecDeque<Snapshot>,
+	///Key events in the order they happened, applied at the start of the frames
+	key_events: VecDeque<KeyEvent>,
+	///Keys pressed and released during the current frame, for FX0A
+	keys_pressed: [bool; 16],
+	keys_released: [bool; 16],
+	///Breakpoint the core stopped at, which is not triggered again when continuing
+	stopped_at_breakpoint: Option<u16>,
+	trace: Option<TraceRecorder>,
+	///Last executed instructions, the newest at the back
+	history: VecDeque<Executed>,
 	//_audio_stream is only stored to allow the audio thread to stay alive
 	_audio_stream: Option<cpal::Stream>,
 }
@@ -208,7 +486,7 @@ impl Core {
 			0b11110000, 0b10000000, 0b11100000, 0b10000000, 0b10000000, // F
 		];
 
-		self.state.memory[0..font.len()].copy_from_slice(&font);
+		self.state.memory[FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE].copy_from_slice(&font);
 	}
 
 	fn load_game(&mut self, path: PathBuf) {
@@ -247,6 +525,77 @@ impl Core {
 
 		self.state.memory[512..(rom.len() + 512)].copy_from_slice(&rom);
 		trace!("ROM loaded");
+
+		let hash = database::hash(&rom);
+		let metadata = database::lookup(&hash);
+		match &metadata {
+			Some(metadata) => {
+				info!("Found '{}' ({}) in the ROM database", metadata.title, hash);
+
+				if let Some(quirks) = metadata.quirks {
+					self.state.quirks = quirks;
+				}
+				if let Some(opcodes_per_frame) = metadata.opcodes_per_frame {
+					self.state.opcodes_per_frame = opcodes_per_frame;
+				}
+			}
+			None => trace!("ROM {} is not in the ROM database", hash),
+		}
+
+		self.state.rom_hash = Some(hash);
+		self.state.rom_metadata = metadata;
+
+		let state_path = save_state::path(&path);
+		self.state.state_saved = state_path.exists();
+		self.state_path = Some(state_path);
+	}
+
+	fn save_state(&mut self) {
+		self.saved_state = Some(Snapshot::take(&self.state));
+		self.state.state_saved = true;
+
+		if let Some(path) = &self.state_path {
+			match SaveState::new(&self.state).save(path) {
+				Ok(()) => info!("State saved to {}", path.display()),
+				Err(e) => error!("Error saving state to {}: {}", path.display(), e),
+			}
+		}
+	}
+
+	fn load_state(&mut self) {
+		if let Some(snapshot) = &self.saved_state {
+			snapshot.restore(&mut self.state);
+		} else {
+			let path = match &self.state_path {
+				Some(path) if path.exists() => path,
+				_ => {
+					warn!("Cannot load state, no state was saved");
+					return;
+				}
+			};
+
+			let save_state = match SaveState::load(path) {
+				Ok(save_state) => save_state,
+				Err(e) => {
+					error!("Error loading state {}: {}", path.display(), e);
+					return;
+				}
+			};
+			if save_state.rom_hash != self.state.rom_hash {
+				warn!(
+					"Cannot load state {}, it belongs to a different ROM",
+					path.display()
+				);
+				return;
+			}
+
+			save_state.restore(&mut self.state);
+		}
+
+		self.state.frames_produced += 1;
+		self.rewind_buffer.clear();
+		self.history.clear();
+		self.stopped_at_breakpoint = None;
 	}
 
 	fn run(&mut self) {
@@ -262,14 +611,18 @@ impl Core {
 			let running = self.state.running;
 			let step_frame = self.state.step_frame;
 
-			if running || step_frame {
+			if self.state.rewinding {
+				self.rewind_frame();
+			} else if running || step_frame {
 				self.state.step_frame = false;
 
+				self.record_rewind_frame();
 				self.step_frame();
 				if self.should_exit() {
 					return;
 				}
 
+				self.state.frames_produced += 1;
 				self.update_frontend();
 
 				self.state.current_frame += 1;
@@ -282,7 +635,13 @@ impl Core {
 
 			//Limit the thread to 60 fps when the core is not running or frame stepping is used
 			//Otherwise limit to the configured fps
-			let desired_fps = if step_frame || !running { 60.0 } else { FPS };
+			let desired_fps = if step_frame || !running {
+				60.0
+			} else if self.state.fast_forward {
+				FPS * FAST_FORWARD_SPEED
+			} else {
+				FPS
+			};
 			trace!("Desired FPS: {}", desired_fps);
 
 			let actual_frame_time = start_of_frame.elapsed();
@@ -339,15 +698,123 @@ impl Core {
 				Event::ChangeVolume(volume) => {
 					self.send_sound_event(audio::Event::ChangeVolume(volume));
 				}
-				Event::KeysDown(keys) => {
-					self.state.previous_keys_down = self.state.keys_down;
-					self.state.keys_down = keys;
+				Event::KeyDown(key, time) => self.queue_key_event(key, true, time),
+				Event::KeyUp(key, time) => self.queue_key_event(key, false, time),
+				Event::ChangeQuirks(quirks) => {
+					self.state.quirks = quirks;
+				}
+				Event::SaveState => self.save_state(),
+				Event::LoadState => self.load_state(),
+				Event::ChangeFastForward(fast_forward) => {
+					self.state.fast_forward = fast_forward;
+				}
+				Event::ChangeRewinding(rewinding) => {
+					self.state.rewinding = rewinding;
+				}
+				Event::WriteMemory { address, bytes } => {
+					let start = address as usize;
+					let end = (start + bytes.len()).min(self.state.memory.len());
+					if end < start + bytes.len() {
+						warn!(
+							"Writing {} bytes at {:#05X} exceeds the memory, the rest is ignored",
+							bytes.len(),
+							address
+						);
+					}
+
+					if start < end {
+						self.state.memory[start..end].copy_from_slice(&bytes[..end - start]);
+					}
+				}
+				Event::SetRegister { register, value } => match register {
+					0..=0xF => self.state.v_registers[register as usize] = value,
+					_ => warn!("Cannot set invalid register V{:X}", register),
+				},
+				Event::SetPc(address) => match address {
+					0..=MAX_PC => {
+						self.state.program_counter = address;
+						self.stopped_at_breakpoint = None;
+					}
+					_ => warn!(
+						"Cannot set PC to {:#05X}, it is outside of the memory",
+						address
+					),
+				},
+				Event::SetI(address) => match address {
+					0..=MAX_I => self.state.i_register = address,
+					_ => warn!(
+						"Cannot set I to {:#05X}, it is outside of the memory",
+						address
+					),
+				},
+				Event::SetTimer { timer, value } => match timer {
+					Timer::Delay => self.state.delay_timer = value,
+					Timer::Sound => self.state.sound_timer = value,
+				},
+				Event::ChangeBreakpoint { address, enabled } => {
+					if enabled {
+						self.state.breakpoints.insert(address);
+					} else {
+						self.state.breakpoints.remove(&address);
+					}
+					//A breakpoint added again later triggers on the next visit
+					if self.stopped_at_breakpoint == Some(address) {
+						self.stopped_at_breakpoint = None;
+					}
+				}
+				Event::StartTrace(trace) => {
+					self.stop_trace();
+					info!("Tracing to {}", trace.path().display());
+
+					self.state.trace_path = Some(trace.path().to_path_buf());
+					self.trace = Some(trace);
+				}
+				Event::StopTrace => self.stop_trace(),
+				Event::ChangeProfiling(enabled) => {
+					self.state.profile = if enabled {
+						Some(Profile::default())
+					} else {
+						None
+					};
+				}
+				Event::ResetProfile => {
+					if let Some(profile) = &mut self.state.profile {
+						*profile = Profile::default();
+					}
+				}
+				Event::ResetCoverage => self.state.coverage = Coverage::default(),
+				Event::LoadCrashReport(report) => {
+					info!("Loading crash report of {}", report.error);
+
+					self.stop_trace();
+					let frames_produced = self.state.frames_produced;
+					self.state = report.restore();
+					self.state.frames_produced = frames_produced + 1;
+					self.send_sound_event(audio::Event::ChangeEnabled(false));
+				}
+				Event::LoadSaveState { save_state, path } => {
+					info!("Loading save state {}", path.display());
+
+					save_state.restore(&mut self.state);
+					self.state.frames_produced += 1;
+					self.saved_state = None;
+					self.state.state_saved = path.exists();
+					self.state_path = Some(path);
+					self.rewind_buffer.clear();
+					self.history.clear();
+					self.stopped_at_breakpoint = None;
 				}
 			}
 
 			event_handled = true;
 		}
 
+		//While paused no frames are run, so the keys are applied right away to keep the frontend up to date.
+		//Taps are not kept, since nothing could read them.
+		if !self.state.running && !self.state.step_frame {
+			self.apply_key_events(false);
+		}
+
 		//Only update the frontend if an event was handled to lower CPU usage
 		if event_handled {
 			self.update_frontend();
@@ -393,8 +860,91 @@ impl Core {
 		}
 	}
 
+	fn record_rewind_frame(&mut self) {
+		if self.rewind_buffer.len() == REWIND_FRAMES {
+			self.rewind_buffer.pop_front();
+		}
+
+		self.rewind_buffer.push_back(Snapshot::take(&self.state));
+	}
+
+	fn rewind_frame(&mut self) {
+		if let Some(snapshot) = self.rewind_buffer.pop_back() {
+			snapshot.restore(&mut self.state);
+			self.state.frames_produced += 1;
+			self.history.clear();
+			self.stopped_at_breakpoint = None;
+			self.update_frontend();
+		}
+	}
+
+	fn queue_key_event(&mut self, key: u8, down: bool, time: Instant) {
+		if key > 0xF {
+			warn!("Ignoring event for invalid key {:#X}", key);
+			return;
+		}
+
+		//Events are usually sent in order, but keep the queue sorted in case they are not
+		let index = self.key_events.partition_point(|event| event.time <= time);
+		self.key_events.insert(index, KeyEvent { key, down, time });
+	}
+
+	///Apply the queued key events to `keys_down`.
+	///With `keep_taps`, every key changes at most once so a press and release in quick succession
+	///is still visible for a whole frame. The remaining events are applied in the next frames.
+	fn apply_key_events(&mut self, keep_taps: bool) {
+		let mut changed = [false; 16];
+		self.keys_pressed = [false; 16];
+		self.keys_released = [false; 16];
+
+		while let Some(event) = self.key_events.front() {
+			let key = event.key as usize;
+			if keep_taps && changed[key] {
+				break;
+			}
+
+			trace!(
+				"Key {:X} {} {:?} ago",
+				key,
+				if event.down { "pressed" } else { "released" },
+				event.time.elapsed()
+			);
+
+			if event.down {
+				self.keys_pressed[key] = true;
+			} else {
+				self.keys_released[key] = true;
+			}
+			self.state.keys_down[key] = event.down;
+			changed[key] = true;
+
+			if self.state.key_history.len() == KEY_HISTORY_LENGTH {
+				self.state.key_history.pop_front();
+			}
+			self.state.key_history.push_back(KeyRecord {
+				frame: self.state.current_frame,
+				key: event.key,
+				down: event.down,
+			});
+
+			self.key_events.pop_front();
+		}
+	}
+
 	fn step_frame(&mut self) {
+		if matches!(&self.trace, Some(trace) if trace.filter().is_finished(self.state.current_frame))
+		{
+			self.stop_trace();
+		}
+
+		self.state.keys_polled = [false; 16];
+		self.apply_key_events(true);
+
 		for opcode in 0..self.state.opcodes_per_frame {
+			if self.check_breakpoint() {
+				return;
+			}
+
 			self.execute_opcode(opcode == 0);
 
 			if self.should_exit() {
@@ -404,6 +954,24 @@ impl Core {
 		self.update_timers();
 	}
 
+	///Pause the core if the next instruction has a breakpoint. Returns whether it was paused.
+	fn check_breakpoint(&mut self) -> bool {
+		let address = self.state.program_counter;
+
+		if !self.state.breakpoints.contains(&address)
+			|| self.stopped_at_breakpoint.take() == Some(address)
+		{
+			return false;
+		}
+
+		info!("Breakpoint at {:#05X}", address);
+
+		self.stopped_at_breakpoint = Some(address);
+		self.state.running = false;
+		self.send_sound_event(audio::Event::ChangeEnabled(false));
+		true
+	}
+
 	fn update_timers(&mut self) {
 		if self.state.delay_timer > 0 {
 			self.state.delay_timer -= 1;
@@ -416,6 +984,14 @@ impl Core {
 	}
 
 	fn execute_opcode(&mut self, first_in_frame: bool) {
+		let pc = self.state.program_counter;
+		let v_registers = self.state.v_registers;
+		let i_register = self.state.i_register;
+
+		if let Some(profile) = &mut self.state.profile {
+			profile.sync_call_stack(&self.state.call_stack, &self.state.memory);
+		}
+
 		let opcode = self.read_16bit_immediate();
 		trace!(
 			"Opcode: {:#06X} at {:#06X}",
@@ -444,21 +1020,139 @@ impl Core {
 			0xF => self.execute_opcode_f(opcode),
 			_ => unreachable!(),
 		}
+
+		let instruction = Instruction::decode(opcode);
+		let waiting = self.waiting_for(pc, instruction);
+
+		if self.state.error.is_none() {
+			if let Some(profile) = &mut self.state.profile {
+				profile.record(self.state.current_frame, pc, opcode, waiting);
+			}
+
+			self.state.coverage.record(
+				self.state.current_frame,
+				pc,
+				instruction,
+				waiting.is_some(),
+				i_register,
+			);
+		}
+
+		//Invalid opcodes and instructions that are still waiting for VBlank or a key did not execute
+		if self.state.error.is_some() || waiting.is_some() {
+			return;
+		}
+
+		if self.trace.is_some() {
+			let record = self.record_instruction(pc, opcode, instruction, v_registers, i_register);
+			self.trace_instruction(&record);
+		}
+
+		if self.history.len() == HISTORY_LENGTH {
+			self.history.pop_front();
+		}
+		self.history.push_back(Executed {
+			frame: self.state.current_frame,
+			pc,
+			opcode,
+			v_registers: self.state.v_registers,
+			i_register: self.state.i_register,
+		});
+	}
+
+	///What the instruction at `pc` is still waiting for, `None` if it finished
+	fn waiting_for(&self, pc: u16, instruction: Option<Instruction>) -> Option<WaitKind> {
+		if self.state.program_counter != pc {
+			return None;
+		}
+
+		match instruction {
+			Some(Instruction::Draw { .. }) => Some(WaitKind::VBlank),
+			Some(Instruction::WaitKey { .. }) => Some(WaitKind::Key),
+			_ => None,
+		}
+	}
+
+	///Describe the executed instruction at `pc`, `v_registers` and `i_register` are the values before it was executed
+	fn record_instruction(
+		&self,
+		pc: u16,
+		opcode: u16,
+		instruction: Option<Instruction>,
+		v_registers: [u8; 16],
+		i_register: u16,
+	) -> TraceRecord {
+		let mut touched: Vec<Touched> = (0..16)
+			.filter(|register| self.state.v_registers[*register] != v_registers[*register])
+			.map(|register| Touched::Register(register as u8, self.state.v_registers[register]))
+			.collect();
+
+		if self.state.i_register != i_register {
+			touched.push(Touched::I(self.state.i_register));
+		}
+
+		if let Some((access, length)) =
+			instruction.and_then(|instruction| instruction.memory_access())
+		{
+			let start = (i_register as usize).min(self.state.memory.len());
+			let end = (start + length as usize).min(self.state.memory.len());
+
+			touched.push(Touched::Memory {
+				access,
+				address: i_register,
+				bytes: self.state.memory[start..end].to_vec(),
+			});
+		}
+
+		TraceRecord {
+			frame: self.state.current_frame,
+			pc,
+			opcode,
+			v_registers: self.state.v_registers,
+			i_register: self.state.i_register,
+			touched,
+		}
+	}
+
+	fn trace_instruction(&mut self, record: &TraceRecord) {
+		if let Some(trace) = &mut self.trace {
+			if let Err(e) = trace.record(record) {
+				error!(
+					"Error writing the trace to {}: {}",
+					trace.path().display(),
+					e
+				);
+				self.stop_trace();
+			}
+		}
+	}
+
+	fn stop_trace(&mut self) {
+		let trace = match self.trace.take() {
+			Some(trace) => trace,
+			None => return,
+		};
+		self.state.trace_path = None;
+
+		let path = trace.path().to_path_buf();
+		let records = trace.records();
+		match trace.finish() {
+			Ok(()) => info!("Wrote {} instructions to {}", records, path.display()),
+			Err(e) => error!("Error writing the trace to {}: {}", path.display(), e),
+		}
 	}
 
 	fn execute_opcode_0(&mut self, opcode: u16) {
 		match opcode {
 			0x00E0 => {
 				//0x00E0 - Clear the display
-				self.state.image.clear(Rgba::BLACK);
+				self.state.image.clear();
 			}
 			0x00EE => {
 				//0x00EE: Return from a subroutine
 				match self.state.call_stack.pop() {
 					Some(pc) => self.state.program_counter = pc,
-					None => self.error(ErrorKind::InvalidReturn {
-						address: self.state.program_counter - 2,
-					}),
+					None => self.invalid_return(),
 				};
 			}
 			_ => {
@@ -506,10 +1200,7 @@ impl Core {
 		//0x5XY0: Skip next instruction if VX equals VY
 		let last_nibble = opcode & 0x000F;
 		if last_nibble != 0x0 {
-			self.error(ErrorKind::InvalidOpcode {
-				opcode,
-				address: self.state.program_counter - 2,
-			});
+			self.invalid_opcode(opcode);
 		}
 
 		let x = (opcode & 0x0F00) >> 8;
@@ -548,28 +1239,34 @@ impl Core {
 				self.state.v_registers[x as usize] = self.state.v_registers[y as usize];
 			}
 			0x1 => {
-				//0x8XY1: Set VX to VX | VY, reset VF to 0
+				//0x8XY1: Set VX to VX | VY, reset VF to 0 (logic quirk)
 				let x = (opcode & 0x0F00) >> 8;
 				let y = (opcode & 0x00F0) >> 4;
 
 				self.state.v_registers[x as usize] |= self.state.v_registers[y as usize];
-				self.state.v_registers[0xF] = 0;
+				if self.state.quirks.logic {
+					self.state.v_registers[0xF] = 0;
+				}
 			}
 			0x2 => {
-				//0x8XY2: Set VX to VX & VY reset VF to 0
+				//0x8XY2: Set VX to VX & VY reset VF to 0 (logic quirk)
 				let x = (opcode & 0x0F00) >> 8;
 				let y = (opcode & 0x00F0) >> 4;
 
 				self.state.v_registers[x as usize] &= self.state.v_registers[y as usize];
-				self.state.v_registers[0xF] = 0;
+				if self.state.quirks.logic {
+					self.state.v_registers[0xF] = 0;
+				}
 			}
 			0x3 => {
-				//0x8XY3: Set VX to VX ^ VY reset VF to 0
+				//0x8XY3: Set VX to VX ^ VY reset VF to 0 (logic quirk)
 				let x = (opcode & 0x0F00) >> 8;
 				let y = (opcode & 0x00F0) >> 4;
 
 				self.state.v_registers[x as usize] ^= self.state.v_registers[y as usize];
-				self.state.v_registers[0xF] = 0;
+				if self.state.quirks.logic {
+					self.state.v_registers[0xF] = 0;
+				}
 			}
 			0x4 => {
 				//0x8XY4: Add VY to VX. Set VF to 1 if there's a carry, 0 otherwise.
@@ -596,11 +1293,13 @@ impl Core {
 			0x6 => {
 				//0x8XY6: Store the least significant bit of VY in VF,
 				//then shift VY to the right by 1 and store the result in VX.
+				//VX is shifted in place instead with the shift quirk.
 				let x = (opcode & 0x0F00) >> 8;
 				let y = (opcode & 0x00F0) >> 4;
+				let source = if self.state.quirks.shift { x } else { y };
 
-				let lsb = self.state.v_registers[y as usize] & 0x1;
-				self.state.v_registers[x as usize] = self.state.v_registers[y as usize] >> 1;
+				let lsb = self.state.v_registers[source as usize] & 0x1;
+				self.state.v_registers[x as usize] = self.state.v_registers[source as usize] >> 1;
 				self.state.v_registers[0xF] = lsb;
 			}
 			0x7 => {
@@ -617,17 +1316,16 @@ impl Core {
 			0xE => {
 				//0x8XYE: Store the most significant bit of VY in VF,
 				//then shift VY to the left by 1 and store the result in VX.
+				//VX is shifted in place instead with the shift quirk.
 				let x = (opcode & 0x0F00) >> 8;
 				let y = (opcode & 0x00F0) >> 4;
+				let source = if self.state.quirks.shift { x } else { y };
 
-				let msb = (self.state.v_registers[x as usize] >> 7) & 0x1;
-				self.state.v_registers[x as usize] = self.state.v_registers[y as usize] << 1;
+				let msb = (self.state.v_registers[source as usize] >> 7) & 0x1;
+				self.state.v_registers[x as usize] = self.state.v_registers[source as usize] << 1;
 				self.state.v_registers[0xF] = msb;
 			}
-			_ => self.error(ErrorKind::InvalidOpcode {
-				opcode,
-				address: self.state.program_counter - 2,
-			}),
+			_ => self.invalid_opcode(opcode),
 		}
 	}
 
@@ -635,10 +1333,7 @@ impl Core {
 		//0x9XY0: Skip next instruction if VX doesn't equal VY
 		let last_nibble = opcode & 0x000F;
 		if last_nibble != 0x0 {
-			self.error(ErrorKind::InvalidOpcode {
-				opcode,
-				address: self.state.program_counter - 2,
-			});
+			self.invalid_opcode(opcode);
 		}
 
 		let x = (opcode & 0x0F00) >> 8;
@@ -657,8 +1352,14 @@ impl Core {
 
 	fn execute_opcode_b(&mut self, opcode: u16) {
 		//0xBNNN: Jump to address NNN plus V0
+		//With the jump quirk this is 0xBXNN: Jump to address XNN plus VX
 		let address = opcode & 0x0FFF;
-		self.state.program_counter = self.state.v_registers[0x0] as u16 + address;
+		let register = if self.state.quirks.jump {
+			(opcode & 0x0F00) >> 8
+		} else {
+			0x0
+		};
+		self.state.program_counter = self.state.v_registers[register as usize] as u16 + address;
 	}
 
 	fn execute_opcode_c(&mut self, opcode: u16) {
@@ -675,7 +1376,7 @@ impl Core {
 		//VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen
 
 		//Rerun the instruction until it is the first in a frame to simulate waiting for VBlank
-		if !first_in_frame {
+		if self.state.quirks.vblank && !first_in_frame {
 			self.state.program_counter -= 2;
 			return;
 		}
@@ -695,27 +1396,30 @@ impl Core {
 		self.state.v_registers[0xF] = 0;
 
 		for row in 0..height {
-			let raw_byte = self.state.memory[self.state.i_register as usize + row];
+			let raw_byte = self.read_mem(self.state.i_register.wrapping_add(row as u16));
 
 			for col in 0..=7 {
-				let x = (x % WIDTH) + col;
-				let y = (y % HEIGHT) + row;
+				let mut x = (x % WIDTH) + col;
+				let mut y = (y % HEIGHT) + row;
 
-				if x > WIDTH - 1 || y > HEIGHT - 1 {
+				if self.state.quirks.wrap {
+					x %= WIDTH;
+					y %= HEIGHT;
+				} else if x > WIDTH - 1 || y > HEIGHT - 1 {
 					continue;
 				}
 
 				let pixel_value = (raw_byte >> (7 - col)) & 0x1;
-				let old_pixel_value = (self.state.image[(x, y)] == Rgba::WHITE) as u8;
+				let old_pixel_value = (self.state.image[(x, y)] != PIXEL_OFF) as u8;
 				self.state.image[(x, y)] = if (pixel_value ^ old_pixel_value) == 1 {
-					Rgba::WHITE
+					PIXEL_ON
 				} else {
 					//Set VF to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen
 					if old_pixel_value == 1 {
 						self.state.v_registers[0xF] = 1;
 					}
 
-					Rgba::BLACK
+					PIXEL_OFF
 				};
 			}
 		}
@@ -740,10 +1444,7 @@ impl Core {
 					self.skip_opcode();
 				}
 			}
-			_ => self.error(ErrorKind::InvalidOpcode {
-				opcode,
-				address: self.state.program_counter - 2,
-			}),
+			_ => self.invalid_opcode(opcode),
 		}
 	}
 
@@ -770,7 +1471,10 @@ impl Core {
 			}
 			0x1E => {
 				//0xFX1E: Add VX to I.
-				self.state.i_register += self.state.v_registers[x as usize] as u16;
+				self.state.i_register = self
+					.state
+					.i_register
+					.wrapping_add(self.state.v_registers[x as usize] as u16);
 			}
 			0x29 => {
 				//0xFX29: Set I to the location of the sprite for the character in VX.
@@ -785,58 +1489,73 @@ impl Core {
 				let ones = vx % 10;
 
 				self.write_mem(self.state.i_register, hundreds);
-				self.write_mem(self.state.i_register + 1, tens);
-				self.write_mem(self.state.i_register + 2, ones);
+				self.write_mem(self.state.i_register.wrapping_add(1), tens);
+				self.write_mem(self.state.i_register.wrapping_add(2), ones);
 			}
 			0x55 => {
 				//0xFX55: Store V0 to VX in memory starting at address I.
 				for i in 0..=x {
-					self.write_mem(self.state.i_register, self.state.v_registers[i as usize]);
-					self.state.i_register += 1;
+					self.write_mem(
+						self.state.i_register.wrapping_add(i),
+						self.state.v_registers[i as usize],
+					);
 				}
+				self.increment_i_after_memory_access(x);
 			}
 			0x65 => {
 				//0xFX65: Read V0 to VX from memory starting at address I.
 				for i in 0..=x {
-					self.state.v_registers[i as usize] = self.read_mem(self.state.i_register);
-					self.state.i_register += 1;
+					self.state.v_registers[i as usize] =
+						self.read_mem(self.state.i_register.wrapping_add(i));
 				}
+				self.increment_i_after_memory_access(x);
 			}
-			_ => self.error(ErrorKind::InvalidOpcode {
-				opcode,
-				address: self.state.program_counter - 2,
-			}),
+			_ => self.invalid_opcode(opcode),
+		}
+	}
+
+	fn increment_i_after_memory_access(&mut self, x: u16) {
+		//FX55 and FX65 increment I by X + 1 unless the memory quirks say otherwise
+		if self.state.quirks.memory_leave_i_unchanged {
+			return;
 		}
+
+		let increment = if self.state.quirks.memory_increment_by_x {
+			x
+		} else {
+			x + 1
+		};
+		self.state.i_register = self.state.i_register.wrapping_add(increment);
 	}
 
 	fn wait_for_key_press(&mut self, register_to_assign: usize) {
 		//Decrement pc to prevent the next opcode from being executed
 		self.state.program_counter -= 2;
+		self.state.keys_polled = [true; 16];
 
-		for key in 0..=0xF {
-			if self.was_key_released(key) {
-				self.state.program_counter += 2;
-				self.state.v_registers[register_to_assign] = key;
-			}
-		}
-	}
+		let triggered = if self.state.quirks.key_wait_release {
+			&mut self.keys_released
+		} else {
+			&mut self.keys_pressed
+		};
 
-	fn is_key_down(&self, key: u8) -> bool {
-		if key > 0xF {
-			//Maybe error instead of returning false?
-			return false;
-		}
+		//Every press or release only continues one FX0A
+		if let Some(key) = (0..16).find(|key| triggered[*key]) {
+			triggered[key] = false;
 
-		self.state.keys_down[key as usize]
+			self.state.program_counter += 2;
+			self.state.v_registers[register_to_assign] = key as u8;
+		}
 	}
 
-	fn was_key_released(&mut self, key: u8) -> bool {
+	fn is_key_down(&mut self, key: u8) -> bool {
 		if key > 0xF {
 			//Maybe error instead of returning false?
 			return false;
 		}
 
-		!self.state.keys_down[key as usize] && self.state.previous_keys_down[key as usize]
+		self.state.keys_polled[key as usize] = true;
+		self.state.keys_down[key as usize]
 	}
 
 	#[inline]
@@ -851,6 +1570,34 @@ impl Core {
 		self.state.program_counter += 2;
 	}
 
+	fn invalid_opcode(&mut self, opcode: u16) {
+		self.error(ErrorKind::InvalidOpcode {
+			opcode,
+			address: self.state.program_counter - 2,
+			history: self.error_history(),
+		});
+	}
+
+	fn invalid_return(&mut self) {
+		self.error(ErrorKind::InvalidReturn {
+			address: self.state.program_counter - 2,
+			history: self.error_history(),
+		});
+	}
+
+	///The last executed instructions as trace records, the newest last
+	fn error_history(&self) -> Vec<TraceRecord> {
+		let mut previous = None;
+		self.history
+			.iter()
+			.map(|executed| {
+				let record = executed.to_record(previous);
+				previous = Some(executed);
+				record
+			})
+			.collect()
+	}
+
 	#[inline]
 	fn error(&mut self, error: ErrorKind) {
 		error!("Core error: {}", error);
@@ -859,14 +1606,15 @@ impl Core {
 		self.update_frontend();
 	}
 
+	///Addresses past the end of memory wrap around to the start
 	#[inline]
 	fn write_mem(&mut self, address: u16, value: u8) {
-		self.state.memory[address as usize] = value;
+		self.state.memory[(address & MAX_I) as usize] = value;
 	}
 
 	#[inline]
 	fn read_mem(&self, address: u16) -> u8 {
-		self.state.memory[address as usize]
+		self.state.memory[(address & MAX_I) as usize]
 	}
 
 	#[inline]
@@ -885,7 +1633,7 @@ impl Core {
 
 pub fn create_and_run(repaint_frontend_callback: Box<dyn Fn() + Send>) -> Chip8Core {
 	//TODO Better starting screen (ROM loading instructions)
-	let state = CoreState::new(PixelBuf::new([WIDTH, HEIGHT]));
+	let state = CoreState::new(Framebuffer::new([WIDTH, HEIGHT]));
 	let (core_state_receiver, core_state_updater) =
 		single_value_channel::channel_starting_with(state.clone());
 
@@ -901,6 +1649,15 @@ pub fn create_and_run(repaint_frontend_callback: Box<dyn Fn() + Send>) -> Chip8C
 			events: frontend_event_receiver,
 			sound_event_sender,
 			repaint_frontend_callback,
+			saved_state: None,
+			state_path: None,
+			rewind_buffer: VecDeque::new(),
+			key_events: VecDeque::new(),
+			keys_pressed: [false; 16],
+			keys_released: [false; 16],
+			stopped_at_breakpoint: None,
+			trace: None,
+			history: VecDeque::with_capacity(HISTORY_LENGTH),
 			_audio_stream: audio_stream,
 		};
 
@@ -914,3 +1671,68 @@ pub fn create_and_run(repaint_frontend_callback: Box<dyn Fn() + Send>) -> Chip8C
 		event_sender: frontend_event_sender,
 	}
 }
+
+///Run the ROM at `path` for `frames` frames on the calling thread, without audio, input or speed limit.
+///`opcodes_per_frame` and `quirks` replace the default and ROM database settings,
+///the executed instructions are written to `trace` if one is given.
+///Returns the final state, or the error if the core stopped with one.
+pub fn run_headless(
+	path: PathBuf,
+	frames: u32,
+	opcodes_per_frame: Option<u32>,
+	quirks: Option<Quirks>,
+	trace: Option<TraceRecorder>,
+) -> Result<CoreState, ErrorKind> {
+	let state = CoreState::new(Framebuffer::new([WIDTH, HEIGHT]));
+
+	//The receiving ends have to stay alive, otherwise updating the state or sending sound events fails
+	let (_state_receiver, state_updater) =
+		single_value_channel::channel_starting_with(state.clone());
+	let (_event_sender, events) = crossbeam_channel::unbounded();
+	let (sound_event_sender, _sound_events) = crossbeam_channel::unbounded();
+
+	let mut core = Core {
+		state,
+		sleep_error_millis: 0.0,
+		state_updater,
+		events,
+		sound_event_sender,
+		repaint_frontend_callback: Box::new(|| {}),
+		saved_state: None,
+		state_path: None,
+		rewind_buffer: VecDeque::new(),
+		key_events: VecDeque::new(),
+		keys_pressed: [false; 16],
+		keys_released: [false; 16],
+		stopped_at_breakpoint: None,
+		trace: None,
+		history: VecDeque::with_capacity(HISTORY_LENGTH),
+		_audio_stream: None,
+	};
+
+	core.initialise();
+	core.load_game(path);
+	if let Some(opcodes_per_frame) = opcodes_per_frame {
+		core.state.opcodes_per_frame = opcodes_per_frame;
+	}
+	if let Some(quirks) = quirks {
+		core.state.quirks = quirks;
+	}
+	core.trace = trace;
+
+	for _ in 0..frames {
+		if core.should_exit() {
+			break;
+		}
+
+		core.step_frame();
+		core.state.current_frame += 1;
+	}
+
+	core.stop_trace();
+
+	match core.state.error {
+		Some(error) => Err(error),
+		None => Ok(core.state),
+	}
+}
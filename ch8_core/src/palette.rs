@@ -0,0 +1,85 @@
+use crate::framebuffer::PIXEL_OFF;
+use serde::{Deserialize, Serialize};
+
+///Colours used to present the monochrome framebuffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+	pub background: [u8; 3],
+	pub foreground: [u8; 3],
+}
+
+impl Palette {
+	pub const CLASSIC: Palette = Palette {
+		background: [0x00, 0x00, 0x00],
+		foreground: [0xFF, 0xFF, 0xFF],
+	};
+
+	pub const AMBER: Palette = Palette {
+		background: [0x1A, 0x0F, 0x00],
+		foreground: [0xFF, 0xB0, 0x00],
+	};
+
+	pub const GREEN_PHOSPHOR: Palette = Palette {
+		background: [0x0A, 0x1A, 0x0A],
+		foreground: [0x33, 0xFF, 0x66],
+	};
+
+	pub const LCD: Palette = Palette {
+		background: [0x9B, 0xBC, 0x0F],
+		foreground: [0x0F, 0x38, 0x0F],
+	};
+
+	///Default colours of the Octo IDE
+	pub const OCTO: Palette = Palette {
+		background: [0x99, 0x66, 0x00],
+		foreground: [0xFF, 0xCC, 0x00],
+	};
+
+	pub const PRESETS: [(&'static str, Palette); 5] = [
+		("Classic", Palette::CLASSIC),
+		("Amber", Palette::AMBER),
+		("Green phosphor", Palette::GREEN_PHOSPHOR),
+		("LCD", Palette::LCD),
+		("Octo", Palette::OCTO),
+	];
+
+	///Name of the preset this palette matches, if any
+	pub fn preset_name(&self) -> Option<&'static str> {
+		Palette::PRESETS
+			.iter()
+			.find(|(_, preset)| preset == self)
+			.map(|(name, _)| *name)
+	}
+
+	///RGBA colour of the palette index `index`
+	pub fn color(&self, index: u8) -> [u8; 4] {
+		let [r, g, b] = if index == PIXEL_OFF {
+			self.background
+		} else {
+			self.foreground
+		};
+
+		[r, g, b, 0xFF]
+	}
+
+	///RGBA colour between the background (`0.0`) and the foreground (`1.0`)
+	pub fn blend(&self, intensity: f32) -> [u8; 4] {
+		let intensity = intensity.clamp(0.0, 1.0);
+		let mix = |background: u8, foreground: u8| {
+			(background as f32 + (foreground as f32 - background as f32) * intensity).round() as u8
+		};
+
+		[
+			mix(self.background[0], self.foreground[0]),
+			mix(self.background[1], self.foreground[1]),
+			mix(self.background[2], self.foreground[2]),
+			0xFF,
+		]
+	}
+}
+
+impl Default for Palette {
+	fn default() -> Self {
+		Palette::CLASSIC
+	}
+}
@@ -0,0 +1,229 @@
+use crate::disassembler::Instruction;
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+pub const CSV_EXTENSION: &str = "csv";
+
+///Instruction that did not finish and is executed again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaitKind {
+	///DXYN waiting for the start of the next frame (vblank quirk)
+	VBlank,
+	///FX0A waiting for a key
+	Key,
+}
+
+///Time spent waiting, each repeated instruction is one cycle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wait {
+	///Frames with at least one waiting cycle
+	pub frames: u64,
+	pub cycles: u64,
+	last_frame: Option<u32>,
+}
+
+impl Wait {
+	fn record(&mut self, frame: u32) {
+		self.cycles += 1;
+		if self.last_frame != Some(frame) {
+			self.last_frame = Some(frame);
+			self.frames += 1;
+		}
+	}
+}
+
+///Instructions executed in a subroutine, identified by the target of the 2NNN calling it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Routine {
+	pub calls: u64,
+	///Instructions of the routine itself
+	pub instructions: u64,
+	///Instructions including the routines it called
+	pub inclusive_instructions: u64,
+}
+
+///Execution counts collected while the profiler is enabled
+#[derive(Clone, Debug)]
+pub struct Profile {
+	///Executions of the instruction at each address
+	pub executions: Vec<u64>,
+	///Executions per first nibble of the opcode
+	pub opcode_classes: [u64; 16],
+	pub instructions: u64,
+	pub vblank_wait: Wait,
+	pub key_wait: Wait,
+	///Routines by call target, `None` is the code outside of any subroutine
+	pub routines: HashMap<Option<u16>, Routine>,
+	///Call targets of the current call stack, the innermost last
+	call_targets: Vec<u16>,
+}
+
+impl Default for Profile {
+	fn default() -> Self {
+		Self {
+			executions: vec![0; 4096],
+			opcode_classes: [0; 16],
+			instructions: 0,
+			vblank_wait: Wait::default(),
+			key_wait: Wait::default(),
+			routines: HashMap::new(),
+			call_targets: vec![],
+		}
+	}
+}
+
+impl Profile {
+	///Rebuild the call targets if the depth of the call stack was changed outside of 2NNN and 00EE,
+	///e.g. by loading a state. The targets are read from the calls before the return addresses.
+	pub fn sync_call_stack(&mut self, call_stack: &[u16], memory: &[u8; 4096]) {
+		if self.call_targets.len() == call_stack.len() {
+			return;
+		}
+
+		self.call_targets = call_stack
+			.iter()
+			.map(|return_address| {
+				let address = return_address.wrapping_sub(2) as usize % memory.len();
+				let opcode =
+					u16::from_be_bytes([memory[address], memory[(address + 1) % memory.len()]]);
+				opcode & 0x0FFF
+			})
+			.collect();
+	}
+
+	///Count the instruction at `pc` that was executed in `frame`, or repeated while waiting
+	pub fn record(&mut self, frame: u32, pc: u16, opcode: u16, waiting: Option<WaitKind>) {
+		match waiting {
+			Some(WaitKind::VBlank) => return self.vblank_wait.record(frame),
+			Some(WaitKind::Key) => return self.key_wait.record(frame),
+			None => {}
+		}
+
+		self.instructions += 1;
+		self.executions[pc as usize % 4096] += 1;
+		self.opcode_classes[(opcode >> 12) as usize] += 1;
+
+		//The instruction belongs to the routine it is in, even if it calls or returns from another one
+		let current = self.call_targets.last().copied();
+		self.routines.entry(current).or_default().instructions += 1;
+
+		self.routines
+			.entry(None)
+			.or_default()
+			.inclusive_instructions += 1;
+		for (index, target) in self.call_targets.iter().enumerate() {
+			//Recursive routines are only counted once
+			if !self.call_targets[..index].contains(target) {
+				self.routines
+					.entry(Some(*target))
+					.or_default()
+					.inclusive_instructions += 1;
+			}
+		}
+
+		match Instruction::decode(opcode) {
+			Some(Instruction::Call(target)) => {
+				self.call_targets.push(target);
+				self.routines.entry(Some(target)).or_default().calls += 1;
+			}
+			Some(Instruction::Return) => {
+				self.call_targets.pop();
+			}
+			_ => {}
+		}
+	}
+
+	///Instructions and waiting cycles
+	pub fn cycles(&self) -> u64 {
+		self.instructions + self.vblank_wait.cycles + self.key_wait.cycles
+	}
+
+	///Share of all cycles in percent
+	pub fn share(&self, cycles: u64) -> f64 {
+		match self.cycles() {
+			0 => 0.0,
+			total => cycles as f64 / total as f64 * 100.0,
+		}
+	}
+
+	///Routines with the most instructions of their own first
+	pub fn hottest_routines(&self) -> Vec<(Option<u16>, Routine)> {
+		let mut routines: Vec<(Option<u16>, Routine)> = self
+			.routines
+			.iter()
+			.map(|(target, routine)| (*target, *routine))
+			.collect();
+		routines.sort_by(|(target_a, a), (target_b, b)| {
+			b.instructions
+				.cmp(&a.instructions)
+				.then(target_a.cmp(target_b))
+		});
+		routines
+	}
+
+	///Addresses that were executed, the most executed first
+	pub fn hottest_addresses(&self) -> Vec<(u16, u64)> {
+		let mut addresses: Vec<(u16, u64)> = self
+			.executions
+			.iter()
+			.enumerate()
+			.filter(|(_, count)| **count > 0)
+			.map(|(address, count)| (address as u16, *count))
+			.collect();
+		addresses.sort_by(|(address_a, a), (address_b, b)| b.cmp(a).then(address_a.cmp(address_b)));
+		addresses
+	}
+
+	///Write the routines, the executed addresses and the waiting cycles as CSV.
+	///The `kind` column tells the sections apart, shares are percentages of all cycles.
+	pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+		writeln!(
+			writer,
+			"kind,address,calls,instructions,share,inclusive_instructions,inclusive_share,frames"
+		)?;
+
+		for (target, routine) in self.hottest_routines() {
+			let address = match target {
+				Some(target) => format!("{:03X}", target),
+				None => "main".into(),
+			};
+			writeln!(
+				writer,
+				"routine,{},{},{},{:.2},{},{:.2},",
+				address,
+				routine.calls,
+				routine.instructions,
+				self.share(routine.instructions),
+				routine.inclusive_instructions,
+				self.share(routine.inclusive_instructions)
+			)?;
+		}
+
+		for (address, count) in self.hottest_addresses() {
+			writeln!(
+				writer,
+				"address,{:03X},,{},{:.2},,,",
+				address,
+				count,
+				self.share(count)
+			)?;
+		}
+
+		for (kind, wait) in [
+			("vblank_wait", self.vblank_wait),
+			("key_wait", self.key_wait),
+		] {
+			writeln!(
+				writer,
+				"{},,,{},{:.2},,,{}",
+				kind,
+				wait.cycles,
+				self.share(wait.cycles),
+				wait.frames
+			)?;
+		}
+
+		writer.flush()
+	}
+}
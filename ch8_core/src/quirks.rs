@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+///Behaviour differences between CHIP-8 interpreters that ROMs rely on.
+///The names follow the quirks of the chip-8-database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+	///8XY1, 8XY2 and 8XY3 reset VF to 0
+	pub logic: bool,
+	///8XY6 and 8XYE shift VX in place instead of storing the shifted VY in VX
+	pub shift: bool,
+	///FX55 and FX65 increment I by X instead of X + 1
+	pub memory_increment_by_x: bool,
+	///FX55 and FX65 leave I unchanged
+	pub memory_leave_i_unchanged: bool,
+	///Sprites wrap around the screen edges instead of being clipped
+	pub wrap: bool,
+	///BXNN jumps to XNN plus VX instead of NNN plus V0
+	pub jump: bool,
+	///DXYN waits for the vertical blank interrupt
+	pub vblank: bool,
+	///FX0A continues when a key is released instead of pressed, like on the COSMAC VIP
+	pub key_wait_release: bool,
+}
+
+impl Quirks {
+	pub const ORIGINAL_CHIP8: Quirks = Quirks {
+		logic: true,
+		shift: false,
+		memory_increment_by_x: false,
+		memory_leave_i_unchanged: false,
+		wrap: false,
+		jump: false,
+		vblank: true,
+		key_wait_release: true,
+	};
+
+	pub const MODERN_CHIP8: Quirks = Quirks {
+		logic: false,
+		vblank: false,
+		key_wait_release: false,
+		..Quirks::ORIGINAL_CHIP8
+	};
+
+	pub const CHIP48: Quirks = Quirks {
+		logic: false,
+		shift: true,
+		memory_increment_by_x: true,
+		memory_leave_i_unchanged: false,
+		wrap: false,
+		jump: true,
+		vblank: false,
+		key_wait_release: false,
+	};
+
+	pub const SUPER_CHIP: Quirks = Quirks {
+		logic: false,
+		shift: true,
+		memory_increment_by_x: false,
+		memory_leave_i_unchanged: true,
+		wrap: false,
+		jump: true,
+		vblank: false,
+		key_wait_release: false,
+	};
+
+	pub const XO_CHIP: Quirks = Quirks {
+		logic: false,
+		shift: false,
+		memory_increment_by_x: false,
+		memory_leave_i_unchanged: false,
+		wrap: true,
+		jump: false,
+		vblank: false,
+		key_wait_release: true,
+	};
+
+	///Quirks of the platform with the chip-8-database id `platform`
+	pub fn for_platform(platform: &str) -> Option<Quirks> {
+		match platform {
+			"originalChip8" | "hybridVIP" => Some(Quirks::ORIGINAL_CHIP8),
+			"modernChip8" => Some(Quirks::MODERN_CHIP8),
+			"chip48" => Some(Quirks::CHIP48),
+			"superchip1" | "superchip" | "megachip8" => Some(Quirks::SUPER_CHIP),
+			"xochip" => Some(Quirks::XO_CHIP),
+			_ => None,
+		}
+	}
+}
+
+impl Default for Quirks {
+	fn default() -> Self {
+		Quirks::ORIGINAL_CHIP8
+	}
+}
+
+///Human readable name of the platform with the chip-8-database id `platform`
+pub fn platform_name(platform: &str) -> &str {
+	match platform {
+		"originalChip8" => "CHIP-8",
+		"hybridVIP" => "CHIP-8 (hybrid VIP)",
+		"modernChip8" => "CHIP-8 (modern)",
+		"chip8x" => "CHIP-8X",
+		"chip48" => "CHIP-48",
+		"superchip1" => "SUPER-CHIP 1.0",
+		"superchip" => "SUPER-CHIP 1.1",
+		"megachip8" => "MEGA-CHIP",
+		"xochip" => "XO-CHIP",
+		platform => platform,
+	}
+}
@@ -0,0 +1,219 @@
+use crate::framebuffer::Framebuffer;
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+use crate::trace::HEADER;
+use crate::{database, rom, screenshot, upscale, CoreState, ErrorKind, KeyRecord, MAX_I, MAX_PC};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+pub const REPORT_EXTENSION: &str = "ch8report";
+///Increased when the report format changes incompatibly
+const REPORT_VERSION: u32 = 1;
+const SCREENSHOT_SCALE: usize = 8;
+
+///Everything needed to inspect an error of the core, saved as a ZIP archive.
+///`report.json` contains the report itself, the other files are for reading it without the emulator.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+	pub version: u32,
+	///Local time the report was created
+	pub created: String,
+	///The error, including the instruction history
+	pub error: ErrorKind,
+	pub rom_name: Option<String>,
+	pub rom_size: Option<usize>,
+	pub rom_hash: Option<String>,
+	pub quirks: Quirks,
+	pub opcodes_per_frame: u32,
+	///Settings of the frontend that created the report
+	pub settings: serde_json::Value,
+	pub machine: MachineState,
+	///Last key events, the newest last
+	pub key_history: Vec<KeyRecord>,
+}
+
+///Machine state at the time of the error
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MachineState {
+	pub current_frame: u32,
+	pub memory: Vec<u8>,
+	pub v_registers: [u8; 16],
+	pub i_register: u16,
+	pub program_counter: u16,
+	pub call_stack: Vec<u16>,
+	pub delay_timer: u8,
+	pub sound_timer: u8,
+	pub keys_down: [bool; 16],
+	pub breakpoints: Vec<u16>,
+	pub image_size: [usize; 2],
+	///Palette indices of the pixels, row by row
+	pub image: Vec<u8>,
+}
+
+impl CrashReport {
+	///Report of the error in `state`, `None` if the core did not stop with an error
+	pub fn new(state: &CoreState, settings: serde_json::Value) -> Option<Self> {
+		let error = state.error.clone()?;
+
+		let mut breakpoints: Vec<u16> = state.breakpoints.iter().copied().collect();
+		breakpoints.sort_unstable();
+
+		Some(Self {
+			version: REPORT_VERSION,
+			created: chrono::Local::now().to_rfc3339(),
+			error,
+			rom_name: state.rom_name.clone(),
+			rom_size: state.rom_size,
+			rom_hash: state.rom_hash.clone(),
+			quirks: state.quirks,
+			opcodes_per_frame: state.opcodes_per_frame,
+			settings,
+			machine: MachineState {
+				current_frame: state.current_frame,
+				memory: state.memory.to_vec(),
+				v_registers: state.v_registers,
+				i_register: state.i_register,
+				program_counter: state.program_counter,
+				call_stack: state.call_stack.clone(),
+				delay_timer: state.delay_timer,
+				sound_timer: state.sound_timer,
+				keys_down: state.keys_down,
+				breakpoints,
+				image_size: state.image.get_size(),
+				image: state.image.get_pixels().to_vec(),
+			},
+			key_history: state.key_history.iter().copied().collect(),
+		})
+	}
+
+	///Write the report to a ZIP archive at `path`. The screenshot is rendered with `palette`.
+	pub fn save(&self, path: &Path, palette: &Palette) -> io::Result<()> {
+		let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+		let options = FileOptions::default();
+
+		zip.start_file("report.json", options)?;
+		serde_json::to_writer_pretty(&mut zip, self)?;
+
+		zip.start_file("history.tsv", options)?;
+		writeln!(zip, "{}", HEADER)?;
+		for record in self.error.history() {
+			writeln!(zip, "{}", record)?;
+		}
+
+		zip.start_file("input.tsv", options)?;
+		writeln!(zip, "frame\tkey\tevent")?;
+		for record in &self.key_history {
+			let event = if record.down { "down" } else { "up" };
+			writeln!(zip, "{}\t{:X}\t{}", record.frame, record.key, event)?;
+		}
+
+		if let Some(image) = self.image() {
+			let (size, rgba) = image.render(palette, 1);
+			let (size, rgba) = upscale::nearest(size, &rgba, SCREENSHOT_SCALE);
+
+			zip.start_file("screenshot.png", options)?;
+			screenshot::encode_png(&mut zip, size, &rgba)?;
+		}
+
+		zip.finish()?.flush()
+	}
+
+	///Read the report from a ZIP archive written by [`CrashReport::save`]
+	pub fn load(path: &Path) -> io::Result<Self> {
+		let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+		let mut json = String::new();
+		zip.by_name("report.json")?.read_to_string(&mut json)?;
+		let report: Self = serde_json::from_str(&json)?;
+
+		if report.version != REPORT_VERSION {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"Unsupported report version {}, expected {}",
+					report.version, REPORT_VERSION
+				),
+			));
+		}
+		if report.machine.memory.len() != 4096 {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"The memory has {} bytes instead of 4096",
+					report.machine.memory.len()
+				),
+			));
+		}
+		if report.machine.program_counter > MAX_PC || report.machine.i_register > MAX_I {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"The program counter {:#05X} or the I register {:#05X} is outside of the memory",
+					report.machine.program_counter, report.machine.i_register
+				),
+			));
+		}
+
+		Ok(report)
+	}
+
+	fn image(&self) -> Option<Framebuffer> {
+		Framebuffer::from_pixels(self.machine.image_size, self.machine.image.clone())
+	}
+
+	///Core state at the time of the error, paused without the error set so it can be inspected and stepped
+	pub fn restore(&self) -> CoreState {
+		let machine = &self.machine;
+		let image = self
+			.image()
+			.unwrap_or_else(|| Framebuffer::new([crate::WIDTH, crate::HEIGHT]));
+
+		let mut state = CoreState::new(image);
+		state.current_frame = machine.current_frame;
+		state.memory.copy_from_slice(&machine.memory);
+		state.v_registers = machine.v_registers;
+		state.i_register = machine.i_register;
+		state.program_counter = machine.program_counter;
+		state.call_stack = machine.call_stack.clone();
+		state.delay_timer = machine.delay_timer;
+		state.sound_timer = machine.sound_timer;
+		state.keys_down = machine.keys_down;
+		state.breakpoints = machine.breakpoints.iter().copied().collect();
+		state.rom_name = self.rom_name.clone();
+		state.rom_size = self.rom_size;
+		state.rom_hash = self.rom_hash.clone();
+		state.rom_metadata = self.rom_hash.as_deref().and_then(database::lookup);
+		state.quirks = self.quirks;
+		state.opcodes_per_frame = self.opcodes_per_frame;
+		state.key_history = self.key_history.iter().copied().collect();
+
+		state
+	}
+}
+
+impl Debug for CrashReport {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct("CrashReport")
+			.field("created", &self.created)
+			.field("error", &self.error.to_string())
+			.field("rom_name", &self.rom_name)
+			.finish()
+	}
+}
+
+///File name for a report of the ROM `rom_name`, e.g. `pong_crash_2022-12-24_18-30-00.ch8report`
+pub fn file_name(rom_name: Option<&str>) -> String {
+	format!(
+		"{}_crash_{}.{}",
+		rom::rom_file_stem(rom_name.map(Path::new)),
+		chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
+		REPORT_EXTENSION
+	)
+}
@@ -0,0 +1,83 @@
+use crate::quirks::Quirks;
+use crate::report::REPORT_EXTENSION;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::Path;
+
+pub const ROM_EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+pub const SOURCE_EXTENSION: &str = "8o";
+pub const SAVE_STATE_EXTENSION: &str = "ch8state";
+
+///File name of the ROM at `path` without the extension to name exported files, `chip8` without a ROM
+pub fn rom_file_stem(path: Option<&Path>) -> String {
+	path.and_then(Path::file_stem)
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or_else(|| "chip8".into())
+}
+
+///Kinds of files the frontends can open
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+	///Raw CHIP-8 program
+	Chip8,
+	///Raw SUPER-CHIP program
+	SuperChip,
+	///Raw XO-CHIP program
+	XoChip,
+	///Octo assembly source, which cannot be run before Octo exported it as ROM
+	OctoSource,
+	///Machine state written by [`crate::save_state::SaveState::save`]
+	SaveState,
+	///Crash report written by [`crate::report::CrashReport::save`]
+	CrashReport,
+}
+
+impl FileKind {
+	///Detect the kind of file from the extension of `path`, `None` if the extension is unknown
+	pub fn detect(path: &Path) -> Option<Self> {
+		let extension = path
+			.extension()
+			.map(|extension| extension.to_string_lossy().to_lowercase())
+			.unwrap_or_default();
+
+		match extension.as_str() {
+			"ch8" => Some(FileKind::Chip8),
+			"sc8" => Some(FileKind::SuperChip),
+			"xo8" => Some(FileKind::XoChip),
+			SOURCE_EXTENSION => Some(FileKind::OctoSource),
+			SAVE_STATE_EXTENSION => Some(FileKind::SaveState),
+			REPORT_EXTENSION => Some(FileKind::CrashReport),
+			_ => None,
+		}
+	}
+
+	pub fn is_rom(&self) -> bool {
+		matches!(
+			self,
+			FileKind::Chip8 | FileKind::SuperChip | FileKind::XoChip
+		)
+	}
+
+	///Quirks of the platform a SUPER-CHIP or XO-CHIP ROM was written for.
+	///Only the CHIP-8 instructions of these ROMs are emulated.
+	pub fn quirks(&self) -> Option<Quirks> {
+		match self {
+			FileKind::SuperChip => Some(Quirks::SUPER_CHIP),
+			FileKind::XoChip => Some(Quirks::XO_CHIP),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for FileKind {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			FileKind::Chip8 => write!(f, "CHIP-8 ROM"),
+			FileKind::SuperChip => write!(f, "SUPER-CHIP ROM"),
+			FileKind::XoChip => write!(f, "XO-CHIP ROM"),
+			FileKind::OctoSource => write!(f, "Octo source"),
+			FileKind::SaveState => write!(f, "Save state"),
+			FileKind::CrashReport => write!(f, "Crash report"),
+		}
+	}
+}
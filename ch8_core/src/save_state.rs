@@ -0,0 +1,204 @@
+use crate::framebuffer::Framebuffer;
+use crate::quirks::Quirks;
+use crate::rom::SAVE_STATE_EXTENSION;
+use crate::{database, CoreState, MAX_I, MAX_PC};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+///Increased when the save state format changes incompatibly
+const SAVE_STATE_VERSION: u32 = 1;
+
+///Machine state written to a file, to continue a ROM after the emulator was closed.
+///The memory contains the ROM, so a state can be loaded without the ROM file.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveState {
+	pub version: u32,
+	pub rom_name: Option<String>,
+	pub rom_size: Option<usize>,
+	pub rom_hash: Option<String>,
+	pub quirks: Quirks,
+	pub opcodes_per_frame: u32,
+	pub current_frame: u32,
+	pub memory: Vec<u8>,
+	pub v_registers: [u8; 16],
+	pub i_register: u16,
+	pub program_counter: u16,
+	pub call_stack: Vec<u16>,
+	pub delay_timer: u8,
+	pub sound_timer: u8,
+	pub image_size: [usize; 2],
+	///Palette indices of the pixels, row by row
+	pub image: Vec<u8>,
+}
+
+impl SaveState {
+	pub fn new(state: &CoreState) -> Self {
+		Self {
+			version: SAVE_STATE_VERSION,
+			rom_name: state.rom_name.clone(),
+			rom_size: state.rom_size,
+			rom_hash: state.rom_hash.clone(),
+			quirks: state.quirks,
+			opcodes_per_frame: state.opcodes_per_frame,
+			current_frame: state.current_frame,
+			memory: state.memory.to_vec(),
+			v_registers: state.v_registers,
+			i_register: state.i_register,
+			program_counter: state.program_counter,
+			call_stack: state.call_stack.clone(),
+			delay_timer: state.delay_timer,
+			sound_timer: state.sound_timer,
+			image_size: state.image.get_size(),
+			image: state.image.get_pixels().to_vec(),
+		}
+	}
+
+	pub fn save(&self, path: &Path) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		serde_json::to_writer(&mut writer, self)?;
+		writer.flush()
+	}
+
+	///Read a state written by [`SaveState::save`]
+	pub fn load(path: &Path) -> io::Result<Self> {
+		let save_state: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+		let error = if save_state.version != SAVE_STATE_VERSION {
+			format!(
+				"Unsupported save state version {}, expected {}",
+				save_state.version, SAVE_STATE_VERSION
+			)
+		} else if save_state.memory.len() != 4096 {
+			format!(
+				"The memory has {} bytes instead of 4096",
+				save_state.memory.len()
+			)
+		} else if save_state.program_counter > MAX_PC {
+			format!(
+				"The program counter {:#05X} is outside of the memory",
+				save_state.program_counter
+			)
+		} else if save_state.i_register > MAX_I {
+			format!(
+				"The I register {:#05X} is outside of the memory",
+				save_state.i_register
+			)
+		} else if save_state.image().is_none() {
+			format!(
+				"The image does not have {}x{} pixels",
+				save_state.image_size[0], save_state.image_size[1]
+			)
+		} else {
+			return Ok(save_state);
+		};
+
+		Err(io::Error::new(io::ErrorKind::InvalidData, error))
+	}
+
+	fn image(&self) -> Option<Framebuffer> {
+		Framebuffer::from_pixels(self.image_size, self.image.clone())
+	}
+
+	///Restore the machine state, the ROM information and the settings the state was saved with
+	pub fn restore(&self, state: &mut CoreState) {
+		if let Some(image) = self.image() {
+			state.image = image;
+		}
+		state.current_frame = self.current_frame;
+		state.memory.copy_from_slice(&self.memory);
+		state.v_registers = self.v_registers;
+		state.i_register = self.i_register;
+		state.program_counter = self.program_counter;
+		state.call_stack = self.call_stack.clone();
+		state.delay_timer = self.delay_timer;
+		state.sound_timer = self.sound_timer;
+		state.rom_name = self.rom_name.clone();
+		state.rom_size = self.rom_size;
+		state.rom_hash = self.rom_hash.clone();
+		state.rom_metadata = self.rom_hash.as_deref().and_then(database::lookup);
+		state.quirks = self.quirks;
+		state.opcodes_per_frame = self.opcodes_per_frame;
+	}
+}
+
+impl Debug for SaveState {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct("SaveState")
+			.field("rom_name", &self.rom_name)
+			.field("current_frame", &self.current_frame)
+			.finish()
+	}
+}
+
+///Save state file of the ROM at `rom`, e.g. `pong.ch8state` for `pong.ch8`
+pub fn path(rom: &Path) -> PathBuf {
+	rom.with_extension(SAVE_STATE_EXTENSION)
+}
+
+///State the frontend saves on exit to resume the ROM at `rom`, e.g. `pong.auto.ch8state` for `pong.ch8`
+pub fn auto_save_path(rom: &Path) -> PathBuf {
+	rom.with_extension(format!("auto.{}", SAVE_STATE_EXTENSION))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{HEIGHT, WIDTH};
+	use std::fs;
+
+	#[test]
+	fn round_trip() {
+		let mut state = CoreState::new(Framebuffer::new([WIDTH, HEIGHT]));
+		state.memory[0x200] = 0x12;
+		state.v_registers[0xF] = 1;
+		state.program_counter = 0x2A4;
+		state.call_stack = vec![0x202, 0x310];
+		state.image[(3, 4)] = 1;
+		state.rom_hash = Some("0123".into());
+
+		let path = std::env::temp_dir().join("ch8_core_round_trip.ch8state");
+		SaveState::new(&state).save(&path).unwrap();
+		let save_state = SaveState::load(&path).unwrap();
+		fs::remove_file(&path).unwrap();
+
+		let mut restored = CoreState::new(Framebuffer::new([WIDTH, HEIGHT]));
+		save_state.restore(&mut restored);
+		assert_eq!(restored.memory, state.memory);
+		assert_eq!(restored.v_registers, state.v_registers);
+		assert_eq!(restored.program_counter, 0x2A4);
+		assert_eq!(restored.call_stack, [0x202, 0x310]);
+		assert_eq!(restored.image.get_pixels(), state.image.get_pixels());
+		assert_eq!(restored.rom_hash.as_deref(), Some("0123"));
+	}
+
+	#[test]
+	fn invalid_memory() {
+		let mut save_state = SaveState::new(&CoreState::new(Framebuffer::new([WIDTH, HEIGHT])));
+		save_state.memory.truncate(512);
+
+		let path = std::env::temp_dir().join("ch8_core_invalid_memory.ch8state");
+		save_state.save(&path).unwrap();
+		let result = SaveState::load(&path);
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn invalid_program_counter() {
+		let mut save_state = SaveState::new(&CoreState::new(Framebuffer::new([WIDTH, HEIGHT])));
+		save_state.program_counter = 0xFFF;
+
+		let path = std::env::temp_dir().join("ch8_core_invalid_program_counter.ch8state");
+		save_state.save(&path).unwrap();
+		let result = SaveState::load(&path);
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+	}
+}
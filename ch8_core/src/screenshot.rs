@@ -0,0 +1,60 @@
+use crate::framebuffer::Framebuffer;
+use crate::palette::Palette;
+use crate::rom;
+use crate::upscale;
+use crate::upscale::Upscaler;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+///Save `framebuffer` as PNG after applying `palette` and `upscaler`.
+///Every pixel of the upscaled image is then scaled up to a `scale`x`scale` block.
+pub fn save_png(
+	path: &Path,
+	framebuffer: &Framebuffer,
+	palette: &Palette,
+	upscaler: Upscaler,
+	scale: usize,
+) -> io::Result<()> {
+	let (size, buf) = framebuffer.render(palette, 1);
+	let (size, buf) = upscaler.apply(size, &buf);
+	let (size, buf) = upscale::nearest(size, &buf, scale);
+
+	write_png(path, size, &buf)
+}
+
+pub fn write_png(path: &Path, size: [usize; 2], rgba: &[u8]) -> io::Result<()> {
+	let file = File::create(path)?;
+	encode_png(BufWriter::new(file), size, rgba)
+}
+
+pub fn encode_png<W: Write>(writer: W, size: [usize; 2], rgba: &[u8]) -> io::Result<()> {
+	let mut encoder = png::Encoder::new(writer, size[0] as u32, size[1] as u32);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(rgba)?;
+
+	Ok(())
+}
+
+///Timestamped file name for a screenshot, e.g. `pong_2022-12-24_18-30-00.png`
+pub fn file_name(rom_path: Option<&Path>) -> String {
+	format!(
+		"{}_{}.png",
+		rom::rom_file_stem(rom_path),
+		chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+	)
+}
+
+///Path of a new screenshot inside `directory`, or next to the ROM if no directory is configured
+pub fn path(directory: Option<&Path>, rom_path: Option<&Path>) -> PathBuf {
+	let file_name = file_name(rom_path);
+
+	match directory.or_else(|| rom_path.and_then(Path::parent)) {
+		Some(directory) => directory.join(file_name),
+		None => PathBuf::from(file_name),
+	}
+}
@@ -0,0 +1,242 @@
+use crate::disassembler::{Access, Line};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+pub const TRACE_EXTENSION: &str = "tsv";
+
+///Column names of the trace files, separated by tabs.
+///The registers and I are the values after the instruction was executed.
+pub const HEADER: &str = "frame\tpc\topcode\tmnemonic\tV0\tV1\tV2\tV3\tV4\tV5\tV6\tV7\tV8\tV9\tVA\tVB\tVC\tVD\tVE\tVF\tI\ttouched";
+
+///Limits which instructions are recorded, `None` or empty records everything
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+	pub pc: Option<RangeInclusive<u16>>,
+	pub frames: Option<RangeInclusive<u32>>,
+	///First nibbles of the recorded opcodes, e.g. `0xD` for DXYN
+	pub opcode_classes: Vec<u8>,
+}
+
+impl TraceFilter {
+	///Parse a filter from user input: a hex PC range like `200-2FF`, a frame range like `60-120`
+	///and a list of opcode classes like `D, F`. Empty strings do not filter.
+	pub fn parse(pc: &str, frames: &str, opcode_classes: &str) -> Result<Self, String> {
+		let pc = parse_range(pc, 16).map_err(|e| format!("Invalid PC range '{}': {}", pc, e))?;
+		let frames = parse_range(frames, 10)
+			.map_err(|e| format!("Invalid frame range '{}': {}", frames, e))?;
+
+		let opcode_classes = opcode_classes
+			.split(|c: char| c == ',' || c.is_whitespace())
+			.filter(|class| !class.is_empty())
+			.map(|class| match u8::from_str_radix(class, 16) {
+				Ok(class) if class <= 0xF => Ok(class),
+				_ => Err(format!(
+					"Invalid opcode class '{}', expected a hex digit",
+					class
+				)),
+			})
+			.collect::<Result<Vec<u8>, String>>()?;
+
+		Ok(Self {
+			pc: pc.map(|range| *range.start() as u16..=*range.end() as u16),
+			frames,
+			opcode_classes,
+		})
+	}
+
+	pub fn matches(&self, frame: u32, pc: u16, opcode: u16) -> bool {
+		if matches!(&self.pc, Some(range) if !range.contains(&pc))
+			|| matches!(&self.frames, Some(range) if !range.contains(&frame))
+		{
+			return false;
+		}
+
+		self.opcode_classes.is_empty() || self.opcode_classes.contains(&((opcode >> 12) as u8))
+	}
+
+	///Whether no instruction of `frame` or later frames can match
+	pub fn is_finished(&self, frame: u32) -> bool {
+		matches!(&self.frames, Some(range) if frame > *range.end())
+	}
+}
+
+///Parse `start-end` or a single number, with both ends included
+fn parse_range(range: &str, radix: u32) -> Result<Option<RangeInclusive<u32>>, String> {
+	let range = range.trim();
+	if range.is_empty() {
+		return Ok(None);
+	}
+
+	let parse = |number: &str| {
+		let number = number.trim();
+		let number = if radix == 16 {
+			number.trim_start_matches("0x")
+		} else {
+			number
+		};
+		u32::from_str_radix(number, radix).map_err(|e| e.to_string())
+	};
+
+	let (start, end) = match range.split_once('-') {
+		Some((start, end)) => (parse(start)?, parse(end)?),
+		None => {
+			let number = parse(range)?;
+			(number, number)
+		}
+	};
+
+	if start > end {
+		return Err("the start is after the end".into());
+	}
+	if radix == 16 && end > 0xFFF {
+		return Err("addresses end at FFF".into());
+	}
+
+	Ok(Some(start..=end))
+}
+
+///Something an instruction changed or read
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Touched {
+	///V`register` was set to the value
+	Register(u8, u8),
+	I(u16),
+	///Bytes read from or written to memory starting at `address`
+	Memory {
+		access: Access,
+		address: u16,
+		bytes: Vec<u8>,
+	},
+}
+
+impl fmt::Display for Touched {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Touched::Register(register, value) => write!(f, "V{:X}={:02X}", register, value),
+			Touched::I(value) => write!(f, "I={:03X}", value),
+			Touched::Memory {
+				access,
+				address,
+				bytes,
+			} => {
+				let access = match access {
+					Access::Read => "R",
+					Access::Write => "W",
+				};
+				write!(f, "{}[{:03X}]=", access, address)?;
+				for byte in bytes {
+					write!(f, "{:02X}", byte)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+///One executed instruction and the registers after it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRecord {
+	pub frame: u32,
+	pub pc: u16,
+	pub opcode: u16,
+	pub v_registers: [u8; 16],
+	pub i_register: u16,
+	pub touched: Vec<Touched>,
+}
+
+impl TraceRecord {
+	pub fn line(&self) -> Line {
+		Line::new(self.pc, self.opcode)
+	}
+}
+
+///Tab separated line in the format described by [`HEADER`]
+impl fmt::Display for TraceRecord {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}\t{:03X}\t{:04X}\t{}",
+			self.frame,
+			self.pc,
+			self.opcode,
+			self.line()
+		)?;
+		for value in self.v_registers {
+			write!(f, "\t{:02X}", value)?;
+		}
+		write!(f, "\t{:03X}\t", self.i_register)?;
+
+		for (index, touched) in self.touched.iter().enumerate() {
+			if index > 0 {
+				write!(f, " ")?;
+			}
+			write!(f, "{}", touched)?;
+		}
+		Ok(())
+	}
+}
+
+///Writes the executed instructions that match a filter to a file, one line each
+pub struct TraceRecorder {
+	path: PathBuf,
+	writer: BufWriter<File>,
+	filter: TraceFilter,
+	records: u64,
+}
+
+impl TraceRecorder {
+	///Create the trace file at `path` and write the header
+	pub fn create(path: &Path, filter: TraceFilter) -> io::Result<Self> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		writeln!(writer, "{}", HEADER)?;
+
+		Ok(Self {
+			path: path.to_path_buf(),
+			writer,
+			filter,
+			records: 0,
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn filter(&self) -> &TraceFilter {
+		&self.filter
+	}
+
+	pub fn records(&self) -> u64 {
+		self.records
+	}
+
+	pub fn record(&mut self, record: &TraceRecord) -> io::Result<()> {
+		if !self.filter.matches(record.frame, record.pc, record.opcode) {
+			return Ok(());
+		}
+
+		self.records += 1;
+		writeln!(self.writer, "{}", record)
+	}
+
+	///Write the remaining buffered lines
+	pub fn finish(mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
+
+impl Debug for TraceRecorder {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		f.debug_struct("TraceRecorder")
+			.field("path", &self.path)
+			.field("filter", &self.filter)
+			.field("records", &self.records)
+			.finish()
+	}
+}
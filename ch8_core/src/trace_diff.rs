@@ -0,0 +1,685 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs;
+use std::path::Path;
+
+///Values of a trace row that can be compared
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+	Pc,
+	Opcode,
+	V(u8),
+	I,
+}
+
+impl Field {
+	pub const ALL: [Field; 19] = [
+		Field::Pc,
+		Field::Opcode,
+		Field::V(0x0),
+		Field::V(0x1),
+		Field::V(0x2),
+		Field::V(0x3),
+		Field::V(0x4),
+		Field::V(0x5),
+		Field::V(0x6),
+		Field::V(0x7),
+		Field::V(0x8),
+		Field::V(0x9),
+		Field::V(0xA),
+		Field::V(0xB),
+		Field::V(0xC),
+		Field::V(0xD),
+		Field::V(0xE),
+		Field::V(0xF),
+		Field::I,
+	];
+
+	fn index(self) -> usize {
+		match self {
+			Field::Pc => 0,
+			Field::Opcode => 1,
+			Field::V(register) => 2 + register as usize,
+			Field::I => 18,
+		}
+	}
+
+	fn parse(name: &str) -> Option<Self> {
+		Field::ALL
+			.into_iter()
+			.find(|field| field.to_string().eq_ignore_ascii_case(name))
+	}
+}
+
+impl fmt::Display for Field {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Field::Pc => write!(f, "PC"),
+			Field::Opcode => write!(f, "opcode"),
+			Field::V(register) => write!(f, "V{:X}", register),
+			Field::I => write!(f, "I"),
+		}
+	}
+}
+
+///Column of a field, either its position starting at 1 or the name in the header line
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Column {
+	Position(usize),
+	Name(String),
+}
+
+///How to read the fields from the lines of a trace file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceFormat {
+	///Splits the columns, `None` splits at any whitespace
+	delimiter: Option<char>,
+	///Lines skipped at the start of the file, before the header
+	skip: usize,
+	///The registers are logged before the instruction of their row instead of after it
+	registers_before: bool,
+	columns: Vec<(Field, Column)>,
+}
+
+impl TraceFormat {
+	///Format of the traces written by [`crate::trace::TraceRecorder`]
+	pub fn native() -> Self {
+		Self {
+			delimiter: Some('\t'),
+			skip: 0,
+			registers_before: false,
+			columns: Field::ALL
+				.into_iter()
+				.map(|field| (field, Column::Name(field.to_string())))
+				.collect(),
+		}
+	}
+
+	///Parse a column mapping like `delimiter=,;pc=1;opcode=2;V=3;I=19`.
+	///Columns are given by position starting at 1 or by the name in the header line.
+	///`V` maps V0-VF to 16 consecutive columns. `delimiter` is a character, `tab` or `space`
+	///for any whitespace, `skip` is the number of lines before the header or the first row.
+	///`registers` is `after` by default, or `before` for traces that log the registers before the instruction.
+	pub fn parse(mapping: &str) -> Result<Self, String> {
+		let mut format = Self {
+			delimiter: None,
+			skip: 0,
+			registers_before: false,
+			columns: vec![],
+		};
+
+		for entry in mapping
+			.split(';')
+			.map(str::trim)
+			.filter(|entry| !entry.is_empty())
+		{
+			let (key, value) = entry
+				.split_once('=')
+				.ok_or_else(|| format!("Expected key=value, got '{}'", entry))?;
+			let key = key.trim();
+			let value = value.trim();
+
+			match key {
+				"delimiter" => {
+					format.delimiter = match value {
+						"tab" => Some('\t'),
+						"space" => None,
+						_ if value.chars().count() == 1 => value.chars().next(),
+						_ => return Err(format!("Invalid delimiter '{}'", value)),
+					}
+				}
+				"skip" => {
+					format.skip = value
+						.parse()
+						.map_err(|e| format!("Invalid skip '{}': {}", value, e))?
+				}
+				"registers" => {
+					format.registers_before = match value {
+						"before" => true,
+						"after" => false,
+						_ => return Err(format!("Invalid registers '{}'", value)),
+					}
+				}
+				"V" | "v" => {
+					let start = match parse_column(value)? {
+						Column::Position(start) => start,
+						Column::Name(_) => {
+							return Err("V needs the position of the V0 column".into())
+						}
+					};
+					for register in 0..16 {
+						format.columns.push((
+							Field::V(register),
+							Column::Position(start + register as usize),
+						));
+					}
+				}
+				_ => {
+					let field =
+						Field::parse(key).ok_or_else(|| format!("Unknown field '{}'", key))?;
+					format.columns.push((field, parse_column(value)?));
+				}
+			}
+		}
+
+		if !format.columns.iter().any(|(field, _)| *field == Field::Pc) {
+			return Err("The mapping needs a PC column".into());
+		}
+
+		Ok(format)
+	}
+
+	fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+		match self.delimiter {
+			Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+			None => line.split_whitespace().collect(),
+		}
+	}
+}
+
+fn parse_column(value: &str) -> Result<Column, String> {
+	match value.parse::<usize>() {
+		Ok(0) => Err("Column positions start at 1".into()),
+		Ok(position) => Ok(Column::Position(position)),
+		Err(_) => Ok(Column::Name(value.into())),
+	}
+}
+
+///Parse a hex value, which other emulators often prefix with `0x`, `$` or a label like `PC:`
+fn parse_value(text: &str) -> Option<u16> {
+	let text = text.rsplit([':', '=']).next()?.trim();
+	let text = text
+		.trim_start_matches("0x")
+		.trim_start_matches("0X")
+		.trim_start_matches('$');
+	u16::from_str_radix(text, 16).ok()
+}
+
+///Executed instruction read from a trace file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+	///Line in the file, starting at 1
+	pub line: usize,
+	pub text: String,
+	values: [Option<u16>; 19],
+}
+
+impl Row {
+	pub fn value(&self, field: Field) -> Option<u16> {
+		self.values[field.index()]
+	}
+}
+
+///Instructions of a trace file
+pub struct Trace {
+	pub rows: Vec<Row>,
+	///The registers of a row are the ones before its instruction
+	pub registers_before: bool,
+}
+
+impl Trace {
+	pub fn load(path: &Path, format: &TraceFormat) -> Result<Self, String> {
+		let text = fs::read_to_string(path)
+			.map_err(|e| format!("Error reading '{}': {}", path.display(), e))?;
+		Self::parse(&text, format).map_err(|e| format!("Error in '{}': {}", path.display(), e))
+	}
+
+	pub fn parse(text: &str, format: &TraceFormat) -> Result<Self, String> {
+		let mut lines = text
+			.lines()
+			.enumerate()
+			.skip(format.skip)
+			.filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+
+		let needs_header = format
+			.columns
+			.iter()
+			.any(|(_, column)| matches!(column, Column::Name(_)));
+		let header = if needs_header {
+			let (_, header) = lines.next().ok_or("The header line is missing")?;
+			format.split(header)
+		} else {
+			vec![]
+		};
+
+		let columns = format
+			.columns
+			.iter()
+			.map(|(field, column)| {
+				let index = match column {
+					Column::Position(position) => position - 1,
+					Column::Name(name) => header
+						.iter()
+						.position(|column| column.eq_ignore_ascii_case(name))
+						.ok_or_else(|| format!("There is no column '{}'", name))?,
+				};
+				Ok((*field, index))
+			})
+			.collect::<Result<Vec<(Field, usize)>, String>>()?;
+
+		let rows = lines
+			.map(|(index, line)| {
+				let cells = format.split(line);
+				let mut values = [None; 19];
+				for (field, column) in &columns {
+					values[field.index()] = cells.get(*column).and_then(|cell| parse_value(cell));
+				}
+
+				Row {
+					line: index + 1,
+					text: line.into(),
+					values,
+				}
+			})
+			.collect();
+
+		Ok(Self {
+			rows,
+			registers_before: format.registers_before,
+		})
+	}
+}
+
+///Value of a field that differs between the traces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Difference {
+	pub field: Field,
+	pub a: u16,
+	pub b: u16,
+}
+
+///First row where two traces do not match
+#[derive(Clone, Debug)]
+pub struct Divergence {
+	///Rows that matched before the divergence
+	pub matched: usize,
+	///Diverging rows, `None` if a trace ended before the other one
+	pub a: Option<Row>,
+	pub b: Option<Row>,
+	pub differences: Vec<Difference>,
+	///Rows before and after the diverging rows
+	pub context_a: Vec<Row>,
+	pub context_b: Vec<Row>,
+}
+
+impl fmt::Display for Divergence {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		writeln!(
+			f,
+			"Traces diverge after {} matching instructions",
+			self.matched
+		)?;
+
+		for (name, row) in [("A", &self.a), ("B", &self.b)] {
+			match row {
+				Some(row) => writeln!(f, "{} line {}: {}", name, row.line, row.text)?,
+				None => writeln!(f, "{} ended", name)?,
+			}
+		}
+
+		for difference in &self.differences {
+			writeln!(
+				f,
+				"  {}: {:X} != {:X}",
+				difference.field, difference.a, difference.b
+			)?;
+		}
+
+		for (name, row, context) in [
+			("A", &self.a, &self.context_a),
+			("B", &self.b, &self.context_b),
+		] {
+			writeln!(f, "\nContext {}:", name)?;
+			for context_row in context {
+				let marker = if Some(context_row) == row.as_ref() {
+					">"
+				} else {
+					" "
+				};
+				writeln!(f, "{} {:6}: {}", marker, context_row.line, context_row.text)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+///Compare two traces and return the first divergence, with `context` rows before and after it.
+///Traces are aligned at the first instruction both contain, so one can start earlier.
+///Repeated identical rows, like instructions waiting for a key or VBlank, are counted once
+///because emulators log them differently. Only fields both traces contain are compared.
+///The registers after the instruction of a row are compared, for traces that log them before it
+///these are the registers of the next row.
+pub fn diff(a: &Trace, b: &Trace, context: usize) -> Option<Divergence> {
+	let (registers_before_a, registers_before_b) = (a.registers_before, b.registers_before);
+	let a = collapse_repeats(&a.rows);
+	let b = collapse_repeats(&b.rows);
+	let (start_a, start_b) = align(&a, &b);
+
+	let mut matched = 0;
+	loop {
+		let index_a = start_a + matched;
+		let index_b = start_b + matched;
+		let row_a = a.get(index_a);
+		let row_b = b.get(index_b);
+
+		let differences = match (row_a, row_b) {
+			(None, None) => return None,
+			(Some(row_a), Some(row_b)) => differences(
+				[row_a, row_b],
+				[
+					registers_after(&a, index_a, registers_before_a),
+					registers_after(&b, index_b, registers_before_b),
+				],
+			),
+			_ => vec![],
+		};
+
+		if row_a.is_none() || row_b.is_none() || !differences.is_empty() {
+			let context_rows = |rows: &[&Row], index: usize| {
+				let start = index.saturating_sub(context);
+				let end = (index + context + 1).min(rows.len());
+				rows.get(start..end)
+					.unwrap_or_default()
+					.iter()
+					.map(|row| (*row).clone())
+					.collect()
+			};
+
+			return Some(Divergence {
+				matched,
+				a: row_a.map(|row| (*row).clone()),
+				b: row_b.map(|row| (*row).clone()),
+				differences,
+				context_a: context_rows(&a, index_a),
+				context_b: context_rows(&b, index_b),
+			});
+		}
+
+		matched += 1;
+	}
+}
+
+///Differences of the instructions `rows` and the registers after them, which are missing
+///if the registers are logged before each instruction and the trace ended
+fn differences(rows: [&Row; 2], registers: [Option<&Row>; 2]) -> Vec<Difference> {
+	Field::ALL
+		.into_iter()
+		.filter_map(|field| {
+			let [a, b] = match field {
+				Field::Pc | Field::Opcode => rows.map(|row| row.value(field)),
+				Field::V(_) | Field::I => registers.map(|row| row.and_then(|row| row.value(field))),
+			};
+			match (a, b) {
+				(Some(a), Some(b)) if a != b => Some(Difference { field, a, b }),
+				_ => None,
+			}
+		})
+		.collect()
+}
+
+///Row holding the registers after the instruction of the row at `index`
+fn registers_after<'a>(rows: &[&'a Row], index: usize, registers_before: bool) -> Option<&'a Row> {
+	rows.get(index + registers_before as usize).copied()
+}
+
+fn collapse_repeats(rows: &[Row]) -> Vec<&Row> {
+	let mut collapsed: Vec<&Row> = vec![];
+	for row in rows {
+		if collapsed.last().map(|last| last.values) != Some(row.values) {
+			collapsed.push(row);
+		}
+	}
+	collapsed
+}
+
+///Indices of the first rows with the same PC and opcode
+fn align(a: &[&Row], b: &[&Row]) -> (usize, usize) {
+	let same_instruction = |a: &Row, b: &Row| {
+		a.value(Field::Pc) == b.value(Field::Pc)
+			&& (a.value(Field::Opcode).is_none()
+				|| b.value(Field::Opcode).is_none()
+				|| a.value(Field::Opcode) == b.value(Field::Opcode))
+	};
+
+	if let Some(first) = a.first() {
+		if let Some(index) = b.iter().position(|row| same_instruction(first, row)) {
+			return (0, index);
+		}
+	}
+	if let Some(first) = b.first() {
+		if let Some(index) = a.iter().position(|row| same_instruction(row, first)) {
+			return (index, 0);
+		}
+	}
+
+	(0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn trace(text: &str, format: &str) -> Trace {
+		Trace::parse(text, &TraceFormat::parse(format).unwrap()).unwrap()
+	}
+
+	fn rows(trace: &Trace) -> Vec<&Row> {
+		trace.rows.iter().collect()
+	}
+
+	fn pcs(rows: &[&Row]) -> Vec<u16> {
+		rows.iter().filter_map(|row| row.value(Field::Pc)).collect()
+	}
+
+	#[test]
+	fn parse_format() {
+		let format =
+			TraceFormat::parse(" delimiter=space; skip=2 ;pc=1;opcode=op;V=3;I=19;").unwrap();
+		assert_eq!(format.delimiter, None);
+		assert_eq!(format.skip, 2);
+		assert_eq!(format.columns.len(), 19);
+		assert_eq!(format.columns[0], (Field::Pc, Column::Position(1)));
+		assert_eq!(
+			format.columns[1],
+			(Field::Opcode, Column::Name("op".into()))
+		);
+		assert_eq!(format.columns[2], (Field::V(0), Column::Position(3)));
+		assert_eq!(format.columns[17], (Field::V(0xF), Column::Position(18)));
+		assert_eq!(format.columns[18], (Field::I, Column::Position(19)));
+
+		assert_eq!(
+			TraceFormat::parse("delimiter=tab;pc=1").unwrap().delimiter,
+			Some('\t')
+		);
+		assert_eq!(
+			TraceFormat::parse("delimiter=,;PC=1").unwrap().delimiter,
+			Some(',')
+		);
+	}
+
+	#[test]
+	fn parse_format_errors() {
+		for mapping in [
+			"",
+			"opcode=2",
+			"pc",
+			"pc=0",
+			"pc=1;delimiter=ab",
+			"pc=1;skip=x",
+			"pc=1;V=V0",
+			"pc=1;VG=2",
+		] {
+			assert!(TraceFormat::parse(mapping).is_err(), "{}", mapping);
+		}
+	}
+
+	#[test]
+	fn parse_values() {
+		assert_eq!(parse_value("200"), Some(0x200));
+		assert_eq!(parse_value("0x200"), Some(0x200));
+		assert_eq!(parse_value("0XA2F0"), Some(0xA2F0));
+		assert_eq!(parse_value("$2FE"), Some(0x2FE));
+		assert_eq!(parse_value("PC:0x200"), Some(0x200));
+		assert_eq!(parse_value("V3=0A"), Some(0x0A));
+		assert_eq!(parse_value("I: 3FF"), Some(0x3FF));
+		assert_eq!(parse_value(""), None);
+		assert_eq!(parse_value("PC:"), None);
+		assert_eq!(parse_value("xyz"), None);
+	}
+
+	#[test]
+	fn parse_trace() {
+		let text = "Emulator log\n\npc op\n# comment\n200 6005\n202 zz\n204\n";
+		let trace = trace(text, "delimiter=space;skip=1;pc=pc;opcode=op");
+
+		assert_eq!(trace.rows.len(), 3);
+		assert_eq!(trace.rows[0].line, 5);
+		assert_eq!(trace.rows[0].text, "200 6005");
+		assert_eq!(trace.rows[0].value(Field::Opcode), Some(0x6005));
+		assert_eq!(trace.rows[0].value(Field::V(0)), None);
+		assert_eq!(trace.rows[1].value(Field::Opcode), None);
+		assert_eq!(trace.rows[2].value(Field::Pc), Some(0x204));
+		assert_eq!(trace.rows[2].value(Field::Opcode), None);
+
+		let format = TraceFormat::parse("pc=PC;opcode=missing").unwrap();
+		assert!(Trace::parse("PC\topcode\n200\t6005", &format).is_err());
+		assert!(Trace::parse("", &format).is_err());
+	}
+
+	#[test]
+	fn parse_native_trace() {
+		let text = format!(
+			"{}\n0\t200\t6005\tLD V0, 05\t05\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t00\t000\tV0=05",
+			crate::trace::HEADER
+		);
+		let trace = Trace::parse(&text, &TraceFormat::native()).unwrap();
+
+		assert_eq!(trace.rows.len(), 1);
+		assert_eq!(trace.rows[0].value(Field::Pc), Some(0x200));
+		assert_eq!(trace.rows[0].value(Field::Opcode), Some(0x6005));
+		assert_eq!(trace.rows[0].value(Field::V(0)), Some(0x05));
+		assert_eq!(trace.rows[0].value(Field::I), Some(0));
+	}
+
+	#[test]
+	fn collapse() {
+		let trace = trace(
+			"200 D005\n200 D005\n200 D005\n202 1200\n200 D005",
+			"delimiter=space;pc=1;opcode=2",
+		);
+		let collapsed = collapse_repeats(&trace.rows);
+
+		assert_eq!(pcs(&collapsed), [0x200, 0x202, 0x200]);
+		assert_eq!(collapsed[1].line, 4);
+		assert!(collapse_repeats(&[]).is_empty());
+	}
+
+	#[test]
+	fn alignment() {
+		let format = "delimiter=space;pc=1;opcode=2";
+		let a = trace("204 6102\n206 1206", format);
+		let b = trace("200 00E0\n202 6001\n204 6102\n206 1206", format);
+
+		assert_eq!(align(&rows(&a), &rows(&b)), (0, 2));
+		assert_eq!(align(&rows(&b), &rows(&a)), (2, 0));
+
+		//An opcode missing in one trace does not prevent the alignment
+		let pc_only = trace("206\n208", "delimiter=space;pc=1");
+		assert_eq!(align(&rows(&b), &rows(&pc_only)), (3, 0));
+
+		//Same PC with a different opcode, e.g. self-modifying code, does not match
+		let modified = trace("204 6103", format);
+		assert_eq!(align(&rows(&a), &rows(&modified)), (0, 0));
+
+		assert_eq!(align(&[], &rows(&a)), (0, 0));
+	}
+
+	#[test]
+	fn matching_traces() {
+		let format = "delimiter=space;pc=1;opcode=2";
+		let a = trace("200 6005\n202 D005\n202 D005\n204 1200", format);
+		let b = trace("100 0000\n200 6005\n202 D005\n204 1200", format);
+
+		assert!(diff(&a, &b, 2).is_none());
+	}
+
+	#[test]
+	fn divergence() {
+		let a = trace(
+			"200 6005 05\n202 7001 06\n204 7001 07\n206 1206 07",
+			"delimiter=space;pc=1;opcode=2;V0=3",
+		);
+		let b = trace(
+			"200 6005 05\n202 7001 06\n204 7001 08\n206 1206 08",
+			"delimiter=space;pc=1;opcode=2;V0=3",
+		);
+
+		let divergence = diff(&a, &b, 1).unwrap();
+		assert_eq!(divergence.matched, 2);
+		assert_eq!(divergence.a.as_ref().unwrap().line, 3);
+		assert_eq!(
+			divergence.differences,
+			[Difference {
+				field: Field::V(0),
+				a: 0x07,
+				b: 0x08,
+			}]
+		);
+		assert_eq!(divergence.context_a.len(), 3);
+		assert_eq!(divergence.context_b.first().unwrap().line, 2);
+
+		//Fields only one trace has are not compared
+		let without_registers = trace(
+			"200 6005\n202 7001\n204 7001\n206 1206",
+			"delimiter=space;pc=1;opcode=2",
+		);
+		assert!(diff(&a, &without_registers, 1).is_none());
+	}
+
+	#[test]
+	fn trace_ends_early() {
+		let format = "delimiter=space;pc=1";
+		let a = trace("200\n202\n204", format);
+		let b = trace("200\n202", format);
+
+		let divergence = diff(&a, &b, 5).unwrap();
+		assert_eq!(divergence.matched, 2);
+		assert_eq!(divergence.a.unwrap().value(Field::Pc), Some(0x204));
+		assert!(divergence.b.is_none());
+		assert!(divergence.differences.is_empty());
+		assert_eq!(divergence.context_b.len(), 2);
+	}
+
+	#[test]
+	fn registers_before() {
+		let after = trace(
+			"200 6005 05\n202 7001 06\n204 7001 07\n206 1206 07",
+			"delimiter=space;pc=1;opcode=2;V0=3",
+		);
+		let before = trace(
+			"200 6005 00\n202 7001 05\n204 7001 06\n206 1206 07",
+			"delimiter=space;pc=1;opcode=2;V0=3;registers=before",
+		);
+		assert!(diff(&after, &before, 1).is_none());
+		assert!(diff(&before, &after, 1).is_none());
+
+		let diverging = trace(
+			"200 6005 00\n202 7001 05\n204 7001 06\n206 1206 08",
+			"delimiter=space;pc=1;opcode=2;V0=3;registers=before",
+		);
+		let divergence = diff(&after, &diverging, 1).unwrap();
+		assert_eq!(divergence.matched, 2);
+		assert_eq!(divergence.b.unwrap().line, 3);
+		assert_eq!(
+			divergence.differences,
+			[Difference {
+				field: Field::V(0),
+				a: 0x07,
+				b: 0x08,
+			}]
+		);
+
+		assert!(TraceFormat::parse("pc=1;registers=later").is_err());
+	}
+}
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+type Pixel = [u8; 4];
+
+///Pixel-art upscalers that run on the CPU before an image is shown or saved
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Upscaler {
+	None,
+	Scale2x,
+	Scale3x,
+	Epx,
+	///Darkens every third row like the gaps between the scanlines of a CRT
+	Scanlines,
+	///Draws a dark line between all pixels
+	PixelGrid,
+}
+
+impl Upscaler {
+	pub const ALL: [Upscaler; 6] = [
+		Upscaler::None,
+		Upscaler::Scale2x,
+		Upscaler::Scale3x,
+		Upscaler::Epx,
+		Upscaler::Scanlines,
+		Upscaler::PixelGrid,
+	];
+
+	///Factor by which the width and height of an image grow
+	pub fn factor(&self) -> usize {
+		match self {
+			Upscaler::None => 1,
+			Upscaler::Scale2x | Upscaler::Epx => 2,
+			Upscaler::Scale3x | Upscaler::Scanlines => 3,
+			Upscaler::PixelGrid => 4,
+		}
+	}
+
+	///Upscale the RGBA image `rgba` with the size `size`.
+	///Returns the size of the upscaled image and its RGBA bytes.
+	pub fn apply(&self, size: [usize; 2], rgba: &[u8]) -> ([usize; 2], Vec<u8>) {
+		let image = Image { size, rgba };
+		let factor = self.factor();
+		let scaled_size = [size[0] * factor, size[1] * factor];
+
+		let mut buf = vec![0; scaled_size[0] * scaled_size[1] * 4];
+		for y in 0..size[1] {
+			for x in 0..size[0] {
+				//The blocks are arrays on the stack, written straight into the scaled image
+				let mut write = |block: &[Pixel]| {
+					for (i, pixel) in block.iter().enumerate() {
+						let scaled_x = x * factor + i % factor;
+						let scaled_y = y * factor + i / factor;
+						let offset = (scaled_y * scaled_size[0] + scaled_x) * 4;
+
+						buf[offset..offset + 4].copy_from_slice(pixel);
+					}
+				};
+
+				match self {
+					Upscaler::None => write(&[image.pixel(x, y, 0, 0)]),
+					Upscaler::Scale2x => write(&image.scale2x(x, y)),
+					Upscaler::Scale3x => write(&image.scale3x(x, y)),
+					Upscaler::Epx => write(&image.epx(x, y)),
+					Upscaler::Scanlines => write(&image.scanlines(x, y)),
+					Upscaler::PixelGrid => write(&image.pixel_grid(x, y)),
+				}
+			}
+		}
+
+		(scaled_size, buf)
+	}
+}
+
+impl fmt::Display for Upscaler {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Upscaler::None => write!(f, "None"),
+			Upscaler::Scale2x => write!(f, "Scale2x"),
+			Upscaler::Scale3x => write!(f, "Scale3x"),
+			Upscaler::Epx => write!(f, "EPX"),
+			Upscaler::Scanlines => write!(f, "Scanlines"),
+			Upscaler::PixelGrid => write!(f, "Pixel grid"),
+		}
+	}
+}
+
+///Scale every pixel of the RGBA image `rgba` up to a `scale`x`scale` block.
+///Returns the size of the scaled image and its RGBA bytes.
+pub fn nearest(size: [usize; 2], rgba: &[u8], scale: usize) -> ([usize; 2], Vec<u8>) {
+	let scale = scale.max(1);
+	let scaled_size = [size[0] * scale, size[1] * scale];
+
+	let mut buf = Vec::with_capacity(scaled_size[0] * scaled_size[1] * 4);
+	for y in 0..scaled_size[1] {
+		for x in 0..scaled_size[0] {
+			let offset = ((y / scale) * size[0] + x / scale) * 4;
+			buf.extend_from_slice(&rgba[offset..offset + 4]);
+		}
+	}
+
+	(scaled_size, buf)
+}
+
+struct Image<'a> {
+	size: [usize; 2],
+	rgba: &'a [u8],
+}
+
+impl Image<'_> {
+	///Pixel at (`x` + `dx`, `y` + `dy`), coordinates outside the image are clamped to the edge
+	fn pixel(&self, x: usize, y: usize, dx: isize, dy: isize) -> Pixel {
+		let x = (x as isize + dx).clamp(0, self.size[0] as isize - 1) as usize;
+		let y = (y as isize + dy).clamp(0, self.size[1] as isize - 1) as usize;
+		let offset = (y * self.size[0] + x) * 4;
+
+		[
+			self.rgba[offset],
+			self.rgba[offset + 1],
+			self.rgba[offset + 2],
+			self.rgba[offset + 3],
+		]
+	}
+
+	fn scale2x(&self, x: usize, y: usize) -> [Pixel; 4] {
+		//  B
+		//D E F
+		//  H
+		let b = self.pixel(x, y, 0, -1);
+		let d = self.pixel(x, y, -1, 0);
+		let e = self.pixel(x, y, 0, 0);
+		let f = self.pixel(x, y, 1, 0);
+		let h = self.pixel(x, y, 0, 1);
+
+		if b == h || d == f {
+			return [e; 4];
+		}
+
+		[
+			if d == b { d } else { e },
+			if b == f { f } else { e },
+			if d == h { d } else { e },
+			if h == f { f } else { e },
+		]
+	}
+
+	fn scale3x(&self, x: usize, y: usize) -> [Pixel; 9] {
+		//A B C
+		//D E F
+		//G H I
+		let a = self.pixel(x, y, -1, -1);
+		let b = self.pixel(x, y, 0, -1);
+		let c = self.pixel(x, y, 1, -1);
+		let d = self.pixel(x, y, -1, 0);
+		let e = self.pixel(x, y, 0, 0);
+		let f = self.pixel(x, y, 1, 0);
+		let g = self.pixel(x, y, -1, 1);
+		let h = self.pixel(x, y, 0, 1);
+		let i = self.pixel(x, y, 1, 1);
+
+		if b == h || d == f {
+			return [e; 9];
+		}
+
+		let pick = |condition: bool, pixel: Pixel| if condition { pixel } else { e };
+
+		[
+			pick(d == b, d),
+			pick((d == b && e != c) || (b == f && e != a), b),
+			pick(b == f, f),
+			pick((d == b && e != g) || (d == h && e != a), d),
+			e,
+			pick((b == f && e != i) || (h == f && e != c), f),
+			pick(d == h, d),
+			pick((d == h && e != i) || (h == f && e != g), h),
+			pick(h == f, f),
+		]
+	}
+
+	fn epx(&self, x: usize, y: usize) -> [Pixel; 4] {
+		//  A
+		//C P B
+		//  D
+		let a = self.pixel(x, y, 0, -1);
+		let b = self.pixel(x, y, 1, 0);
+		let c = self.pixel(x, y, -1, 0);
+		let d = self.pixel(x, y, 0, 1);
+		let p = self.pixel(x, y, 0, 0);
+
+		//Keep the pixel if three or more of its neighbours are identical
+		let neighbours = [a, b, c, d];
+		if neighbours
+			.iter()
+			.any(|n| neighbours.iter().filter(|other| *other == n).count() >= 3)
+		{
+			return [p; 4];
+		}
+
+		[
+			if c == a { a } else { p },
+			if a == b { b } else { p },
+			if d == c { c } else { p },
+			if b == d { d } else { p },
+		]
+	}
+
+	fn scanlines(&self, x: usize, y: usize) -> [Pixel; 9] {
+		let pixel = self.pixel(x, y, 0, 0);
+		let scanline = darken(pixel, 0.4);
+
+		[
+			pixel, pixel, pixel, //
+			pixel, pixel, pixel, //
+			scanline, scanline, scanline,
+		]
+	}
+
+	fn pixel_grid(&self, x: usize, y: usize) -> [Pixel; 16] {
+		let pixel = self.pixel(x, y, 0, 0);
+		let line = darken(pixel, 0.6);
+
+		[
+			pixel, pixel, pixel, line, //
+			pixel, pixel, pixel, line, //
+			pixel, pixel, pixel, line, //
+			line, line, line, line,
+		]
+	}
+}
+
+fn darken(pixel: Pixel, brightness: f32) -> Pixel {
+	let [r, g, b, a] = pixel;
+	let darken = |channel: u8| (channel as f32 * brightness).round() as u8;
+
+	[darken(r), darken(g), darken(b), a]
+}
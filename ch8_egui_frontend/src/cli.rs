@@ -0,0 +1,229 @@
+use ch8_core::quirks::Quirks;
+use ch8_core::rom;
+use ch8_core::trace;
+use ch8_core::trace::{TraceFilter, TraceRecorder};
+use ch8_core::trace_diff;
+use ch8_core::trace_diff::{Trace, TraceFormat};
+use log::info;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage:
+  ch8_egui_frontend
+      Start the emulator
+  ch8_egui_frontend open <FILE>
+      Start the emulator with a ROM or a crash report, which is shown paused at its error
+  ch8_egui_frontend trace <ROM> [OPTIONS]
+      Run a ROM without window, audio or input and write the executed instructions to a file
+      -o, --output <FILE>            Trace file, <ROM name>.tsv by default
+      --frames <N>                   Frames to run, 600 or the end of --trace-frames by default
+      --opcodes-per-frame <N>        Instructions per frame instead of the default or ROM database speed
+      --quirks <PLATFORM>            Quirks of originalChip8, modernChip8, chip48, superchip or xochip
+                                     instead of the default or ROM database quirks
+      --pc <START-END>               Only record instructions in this hex address range
+      --trace-frames <START-END>     Only record these frames, tracing stops after the last one
+      --opcodes <CLASSES>            Only record these opcode classes, e.g. D,F
+  ch8_egui_frontend diff <TRACE A> <TRACE B> [OPTIONS]
+      Show where two traces diverge
+      Traces of this emulator hold the registers after each instruction,
+      add 'registers=before' to the mapping of traces that log them before it
+      --format-a <MAPPING>           Columns of trace A if it was not written by this emulator
+      --format-b <MAPPING>           Columns of trace B, e.g. 'delimiter=space;pc=1;opcode=2;V=3;I=19'
+                                     Columns are positions starting at 1 or names in the header line,
+                                     V maps V0-VF to 16 consecutive columns,
+                                     the delimiter is a character, tab or space, skip=N skips lines
+      --context <N>                  Instructions shown around the divergence, default: 5
+  ch8_egui_frontend help
+      Show this message";
+
+const DEFAULT_FRAMES: u32 = 600;
+const DEFAULT_CONTEXT: usize = 5;
+
+pub enum Command {
+	///Start the GUI and open the file if one is given
+	Gui(Option<PathBuf>),
+	Help,
+	Trace {
+		rom: PathBuf,
+		output: PathBuf,
+		frames: u32,
+		opcodes_per_frame: Option<u32>,
+		quirks: Option<Quirks>,
+		filter: TraceFilter,
+	},
+	Diff {
+		a: PathBuf,
+		b: PathBuf,
+		format_a: TraceFormat,
+		format_b: TraceFormat,
+		context: usize,
+	},
+}
+
+///Parse the command line arguments without the program name
+pub fn parse(args: &[String]) -> Result<Command, String> {
+	let (command, args) = match args.split_first() {
+		Some((command, args)) => (command.as_str(), args),
+		None => return Ok(Command::Gui(None)),
+	};
+
+	match command {
+		"open" => match args {
+			[file] => Ok(Command::Gui(Some(PathBuf::from(file)))),
+			_ => Err("Expected one file to open".into()),
+		},
+		"trace" => parse_trace(args),
+		"diff" => parse_diff(args),
+		"help" | "-h" | "--help" => Ok(Command::Help),
+		_ => Err(format!("Unknown command '{}'", command)),
+	}
+}
+
+fn parse_trace(args: &[String]) -> Result<Command, String> {
+	let mut rom = None;
+	let mut output = None;
+	let mut frames = None;
+	let mut opcodes_per_frame = None;
+	let mut quirks = None;
+	let mut pc = String::new();
+	let mut trace_frames = String::new();
+	let mut opcode_classes = String::new();
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let mut value = || {
+			args.next()
+				.cloned()
+				.ok_or_else(|| format!("Missing value for {}", arg))
+		};
+
+		match arg.as_str() {
+			"-o" | "--output" => output = Some(PathBuf::from(value()?)),
+			"--frames" => frames = Some(parse_number(arg, &value()?)?),
+			"--opcodes-per-frame" => opcodes_per_frame = Some(parse_number(arg, &value()?)?),
+			"--quirks" => {
+				let platform = value()?;
+				quirks = Some(
+					Quirks::for_platform(&platform)
+						.ok_or_else(|| format!("Unknown platform '{}' for --quirks", platform))?,
+				);
+			}
+			"--pc" => pc = value()?,
+			"--trace-frames" => trace_frames = value()?,
+			"--opcodes" => opcode_classes = value()?,
+			_ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+			_ if rom.is_none() => rom = Some(PathBuf::from(arg)),
+			_ => return Err(format!("Unexpected argument '{}'", arg)),
+		}
+	}
+
+	let rom = rom.ok_or("Missing ROM")?;
+	let filter = TraceFilter::parse(&pc, &trace_frames, &opcode_classes)?;
+
+	let output = output.unwrap_or_else(|| {
+		let name = rom::rom_file_stem(Some(&rom));
+		PathBuf::from(format!("{}.{}", name, trace::TRACE_EXTENSION))
+	});
+
+	let frames = frames.unwrap_or_else(|| match &filter.frames {
+		Some(range) => range.end().saturating_add(1),
+		None => DEFAULT_FRAMES,
+	});
+
+	Ok(Command::Trace {
+		rom,
+		output,
+		frames,
+		opcodes_per_frame,
+		quirks,
+		filter,
+	})
+}
+
+fn parse_diff(args: &[String]) -> Result<Command, String> {
+	let mut traces = vec![];
+	let mut format_a = TraceFormat::native();
+	let mut format_b = TraceFormat::native();
+	let mut context = DEFAULT_CONTEXT;
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let mut value = || {
+			args.next()
+				.cloned()
+				.ok_or_else(|| format!("Missing value for {}", arg))
+		};
+
+		match arg.as_str() {
+			"--format-a" => format_a = TraceFormat::parse(&value()?)?,
+			"--format-b" => format_b = TraceFormat::parse(&value()?)?,
+			"--context" => context = parse_number(arg, &value()?)? as usize,
+			_ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+			_ => traces.push(PathBuf::from(arg)),
+		}
+	}
+
+	match <[PathBuf; 2]>::try_from(traces) {
+		Ok([a, b]) => Ok(Command::Diff {
+			a,
+			b,
+			format_a,
+			format_b,
+			context,
+		}),
+		Err(_) => Err("Expected two traces".into()),
+	}
+}
+
+fn parse_number(option: &str, value: &str) -> Result<u32, String> {
+	value
+		.parse()
+		.map_err(|e| format!("Invalid value '{}' for {}: {}", value, option, e))
+}
+
+///Run a command that does not need the GUI
+pub fn run(command: Command) -> Result<(), String> {
+	match command {
+		Command::Gui(_) => unreachable!("The GUI is started by main"),
+		Command::Help => {
+			println!("{}", USAGE);
+			Ok(())
+		}
+		Command::Trace {
+			rom,
+			output,
+			frames,
+			opcodes_per_frame,
+			quirks,
+			filter,
+		} => {
+			let recorder = TraceRecorder::create(&output, filter)
+				.map_err(|e| format!("Error creating trace file '{}': {}", output.display(), e))?;
+
+			info!("Tracing {} frames of {}", frames, rom.display());
+			ch8_core::run_headless(rom, frames, opcodes_per_frame, quirks, Some(recorder))
+				.map_err(|e| e.to_string())?;
+
+			println!("Trace written to {}", output.display());
+			Ok(())
+		}
+		Command::Diff {
+			a,
+			b,
+			format_a,
+			format_b,
+			context,
+		} => {
+			let trace_a = Trace::load(&a, &format_a)?;
+			let trace_b = Trace::load(&b, &format_b)?;
+
+			match trace_diff::diff(&trace_a, &trace_b, context) {
+				Some(divergence) => {
+					println!("A: {}\nB: {}\n", a.display(), b.display());
+					print!("{}", divergence);
+				}
+				None => println!("The traces match"),
+			}
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,154 @@
+use ch8_core::coverage;
+use ch8_core::coverage::{Coverage, MAP_WIDTH};
+use ch8_core::CoreState;
+use eframe::egui;
+use egui::Color32;
+
+const CELL_SIZE: f32 = 5.0;
+
+pub enum CoverageAction {
+	Reset,
+	ExportCsv,
+	ExportPng,
+}
+
+///Map of how the ROM accessed each byte of the memory, to tell code from data
+pub struct CoverageView {
+	pub open: bool,
+}
+
+impl CoverageView {
+	pub fn new() -> Self {
+		Self { open: false }
+	}
+
+	pub fn show(&mut self, ctx: &egui::Context, state: &CoreState) -> Option<CoverageAction> {
+		if !self.open {
+			return None;
+		}
+
+		let mut open = self.open;
+		let mut action = None;
+
+		egui::Window::new("Memory map")
+			.open(&mut open)
+			.resizable(false)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					if ui.button("Reset").clicked() {
+						action = Some(CoverageAction::Reset);
+					}
+					if ui.button("Export CSV…").clicked() {
+						action = Some(CoverageAction::ExportCsv);
+					}
+					if ui.button("Export PNG…").clicked() {
+						action = Some(CoverageAction::ExportPng);
+					}
+				});
+
+				show_map(ui, &state.coverage);
+				show_legend(ui, &state.coverage);
+				show_self_modifications(ui, &state.coverage);
+			});
+
+		self.open = open;
+
+		action
+	}
+}
+
+fn color(flags: u8) -> Color32 {
+	let [r, g, b] = Coverage::color(flags);
+	Color32::from_rgb(r, g, b)
+}
+
+fn show_map(ui: &mut egui::Ui, coverage: &Coverage) {
+	let rows = coverage.flags.len() / MAP_WIDTH;
+	let size = egui::Vec2::new(MAP_WIDTH as f32, rows as f32) * CELL_SIZE;
+	let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+	let painter = ui.painter_at(rect);
+	for (address, flags) in coverage.flags.iter().enumerate() {
+		let position = egui::Vec2::new((address % MAP_WIDTH) as f32, (address / MAP_WIDTH) as f32);
+		painter.rect_filled(
+			egui::Rect::from_min_size(
+				rect.min + position * CELL_SIZE,
+				egui::Vec2::splat(CELL_SIZE),
+			),
+			0.0,
+			color(*flags),
+		);
+	}
+
+	if let Some(pointer) = response.hover_pos() {
+		let position = (pointer - rect.min) / CELL_SIZE;
+		let address = position.y as usize * MAP_WIDTH + position.x as usize;
+
+		if let Some(flags) = coverage.flags.get(address) {
+			response.on_hover_text(format!("{:03X}: {}", address, describe(*flags)));
+		}
+	}
+}
+
+fn describe(flags: u8) -> String {
+	let names: Vec<&str> = [
+		(coverage::EXECUTED, "executed"),
+		(coverage::READ, "read"),
+		(coverage::WRITTEN, "written"),
+		(coverage::SELF_MODIFIED, "self-modified"),
+	]
+	.into_iter()
+	.filter(|(flag, _)| flags & flag != 0)
+	.map(|(_, name)| name)
+	.collect();
+
+	if names.is_empty() {
+		"unused".into()
+	} else {
+		names.join(", ")
+	}
+}
+
+fn show_legend(ui: &mut egui::Ui, coverage: &Coverage) {
+	egui::Grid::new("coverage_legend").show(ui, |ui| {
+		for (flag, name) in [
+			(coverage::EXECUTED, "Executed"),
+			(coverage::READ, "Read (DXYN, FX65)"),
+			(coverage::WRITTEN, "Written (FX33, FX55)"),
+			(coverage::SELF_MODIFIED, "Executed, then written"),
+		] {
+			let (rect, _) =
+				ui.allocate_exact_size(egui::Vec2::splat(CELL_SIZE * 2.0), egui::Sense::hover());
+			ui.painter().rect_filled(rect, 0.0, color(flag));
+
+			ui.label(name);
+			ui.monospace(format!("{} bytes", coverage.count(flag)));
+			ui.end_row();
+		}
+	});
+	ui.weak("Bytes with several kinds of access mix the colors");
+}
+
+fn show_self_modifications(ui: &mut egui::Ui, coverage: &Coverage) {
+	let modifications = &coverage.self_modifications;
+	if modifications.is_empty() {
+		ui.weak("No self-modifying code");
+		return;
+	}
+
+	ui.collapsing(
+		format!("Self-modifying code ({} writes)", modifications.len()),
+		|ui| {
+			egui::ScrollArea::vertical()
+				.max_height(150.0)
+				.show(ui, |ui| {
+					for modification in modifications {
+						ui.monospace(format!(
+							"Frame {:5}: {:03X} wrote {:03X}",
+							modification.frame, modification.pc, modification.address
+						));
+					}
+				});
+		},
+	);
+}
@@ -0,0 +1,328 @@
+use ch8_core::disassembler;
+use ch8_core::trace::TraceFilter;
+use ch8_core::{CoreState, ErrorKind, Event, Timer};
+use eframe::egui;
+use egui::RichText;
+
+///Disassembled instructions shown before and after the address of an error
+const ERROR_CONTEXT_LINES: usize = 8;
+
+///Values shown by the register inspector
+#[derive(Clone, Default, PartialEq, Eq)]
+struct Registers {
+	v_registers: [u8; 16],
+	i_register: u16,
+	program_counter: u16,
+	delay_timer: u8,
+	sound_timer: u8,
+	call_stack: Vec<u16>,
+}
+
+impl Registers {
+	fn new(state: &CoreState) -> Self {
+		Self {
+			v_registers: state.v_registers,
+			i_register: state.i_register,
+			program_counter: state.program_counter,
+			delay_timer: state.delay_timer,
+			sound_timer: state.sound_timer,
+			call_stack: state.call_stack.clone(),
+		}
+	}
+}
+
+///Inspector for the registers, timers and call stack
+#[derive(Default)]
+pub struct RegisterInspector {
+	///Values at the end of the previous frame, to highlight what the last step changed
+	previous: Registers,
+	current: Registers,
+	frame: u32,
+}
+
+impl RegisterInspector {
+	///Returns the events that apply the edited values
+	pub fn show(&mut self, ui: &mut egui::Ui, state: &CoreState) -> Vec<Event> {
+		let registers = Registers::new(state);
+		if state.current_frame != self.frame {
+			self.frame = state.current_frame;
+			self.previous = std::mem::replace(&mut self.current, registers);
+		} else {
+			self.current = registers;
+		}
+
+		let editable = !state.running && state.error.is_none();
+		let mut events = vec![];
+
+		egui::Grid::new("registers").striped(true).show(ui, |ui| {
+			ui.strong("");
+			ui.strong("Hex");
+			ui.strong("Dec");
+			ui.strong("Bin");
+			ui.end_row();
+
+			let registers = self
+				.current
+				.v_registers
+				.iter()
+				.zip(self.previous.v_registers);
+			for (register, (&current, previous)) in registers.enumerate() {
+				let mut value = current;
+				let name = format!("V{:X}", register);
+				let changed = value != previous;
+
+				if show_value(ui, &name, &mut value, 0xFF, changed, editable) {
+					events.push(Event::SetRegister {
+						register: register as u8,
+						value,
+					});
+				}
+			}
+
+			let mut value = self.current.i_register;
+			let changed = value != self.previous.i_register;
+			if show_value(ui, "I", &mut value, ch8_core::MAX_I, changed, editable) {
+				events.push(Event::SetI(value));
+			}
+
+			let mut value = self.current.program_counter;
+			let changed = value != self.previous.program_counter;
+			if show_value(ui, "PC", &mut value, ch8_core::MAX_PC, changed, editable) {
+				events.push(Event::SetPc(value));
+			}
+
+			for (timer, mut value, previous) in [
+				(
+					Timer::Delay,
+					self.current.delay_timer,
+					self.previous.delay_timer,
+				),
+				(
+					Timer::Sound,
+					self.current.sound_timer,
+					self.previous.sound_timer,
+				),
+			] {
+				let changed = value != previous;
+				if show_value(ui, &timer.to_string(), &mut value, 0xFF, changed, editable) {
+					events.push(Event::SetTimer { timer, value });
+				}
+			}
+		});
+
+		ui.separator();
+
+		let stack_changed = self.current.call_stack != self.previous.call_stack;
+		ui.label(highlight(
+			ui,
+			RichText::new(format!("Call stack ({})", self.current.call_stack.len())),
+			stack_changed,
+		));
+
+		if self.current.call_stack.is_empty() {
+			ui.weak("Empty");
+		}
+
+		//Innermost call first
+		for (depth, return_address) in self.current.call_stack.iter().enumerate().rev() {
+			ui.label(
+				RichText::new(format!(
+					"{:2}: {:03X}, called from {:03X}",
+					depth,
+					return_address,
+					return_address.wrapping_sub(2)
+				))
+				.monospace(),
+			);
+		}
+
+		if !editable {
+			ui.weak("Pause to edit the values");
+		}
+
+		events
+	}
+}
+
+///Show a grid row for a register, edits are limited to `max`. Returns whether the value was edited.
+fn show_value<T: Into<u16> + egui::emath::Numeric>(
+	ui: &mut egui::Ui,
+	name: &str,
+	value: &mut T,
+	max: u16,
+	changed: bool,
+	editable: bool,
+) -> bool {
+	let bits = std::mem::size_of::<T>() * 8;
+	//Addresses are 12 bits wide
+	let (hex_width, bin_width) = if bits == 8 { (2, 8) } else { (3, 12) };
+	let number: u16 = (*value).into();
+
+	ui.label(highlight(ui, RichText::new(name), changed));
+	ui.label(highlight(
+		ui,
+		RichText::new(format!("{:0width$X}", number, width = hex_width)).monospace(),
+		changed,
+	));
+
+	let edited = if editable {
+		ui.add(egui::DragValue::new(value).clamp_range(0..=max))
+			.changed()
+	} else {
+		ui.label(highlight(
+			ui,
+			RichText::new(number.to_string()).monospace(),
+			changed,
+		));
+		false
+	};
+
+	ui.label(highlight(
+		ui,
+		RichText::new(format!("{:0width$b}", number, width = bin_width)).monospace(),
+		changed,
+	));
+	ui.end_row();
+
+	edited
+}
+
+fn highlight(ui: &egui::Ui, text: RichText, changed: bool) -> RichText {
+	if changed {
+		text.color(ui.visuals().warn_fg_color)
+	} else {
+		text
+	}
+}
+
+pub enum TraceAction {
+	Start(TraceFilter),
+	Stop,
+}
+
+///Filter inputs and start/stop buttons for the execution trace
+#[derive(Default)]
+pub struct TraceControls {
+	pc: String,
+	frames: String,
+	opcode_classes: String,
+	error: Option<String>,
+}
+
+impl TraceControls {
+	pub fn show(&mut self, ui: &mut egui::Ui, state: &CoreState) -> Option<TraceAction> {
+		let mut action = None;
+
+		ui.add_enabled_ui(state.trace_path.is_none(), |ui| {
+			egui::Grid::new("trace_filter").show(ui, |ui| {
+				for (label, text, hint) in [
+					("PC", &mut self.pc, "200-2FF"),
+					("Frames", &mut self.frames, "60-120"),
+					("Opcodes", &mut self.opcode_classes, "D, F"),
+				] {
+					ui.label(label);
+					ui.add(
+						egui::TextEdit::singleline(text)
+							.hint_text(hint)
+							.desired_width(100.0),
+					);
+					ui.end_row();
+				}
+			});
+		});
+		ui.weak("Empty filters record everything");
+
+		match &state.trace_path {
+			Some(path) => {
+				ui.label(format!("Tracing to {}", path.display()));
+				if ui.button("Stop trace").clicked() {
+					action = Some(TraceAction::Stop);
+				}
+			}
+			None => {
+				if ui.button("Start trace…").clicked() {
+					match TraceFilter::parse(&self.pc, &self.frames, &self.opcode_classes) {
+						Ok(filter) => {
+							self.error = None;
+							action = Some(TraceAction::Start(filter));
+						}
+						Err(e) => self.error = Some(e),
+					}
+				}
+			}
+		}
+
+		if let Some(error) = &self.error {
+			ui.colored_label(ui.visuals().error_fg_color, error);
+		}
+
+		action
+	}
+}
+
+///Show the disassembly around the instruction that caused `error` and the instructions executed before it
+pub fn show_error_details(ui: &mut egui::Ui, error: &ErrorKind, memory: &[u8; 4096]) {
+	let address = match error.address() {
+		Some(address) => address,
+		None => return,
+	};
+
+	ui.collapsing("Disassembly", |ui| {
+		//Keep the alignment of the address, the ROM may have jumped into the middle of an instruction
+		let lines_before = (address as usize / 2).min(ERROR_CONTEXT_LINES);
+		let start = address - lines_before as u16 * 2;
+
+		let lines =
+			disassembler::disassemble(memory, start, lines_before + ERROR_CONTEXT_LINES + 1);
+
+		for line in lines {
+			let current = line.address == address;
+			let marker = if current { "▶" } else { " " };
+			let text = RichText::new(format!(
+				"{} {:03X}  {:04X}  {}",
+				marker, line.address, line.opcode, line
+			))
+			.monospace();
+
+			if current {
+				ui.label(text.strong().color(ui.visuals().error_fg_color));
+			} else {
+				ui.label(text);
+			}
+		}
+	});
+
+	let history = error.history();
+	ui.collapsing(format!("Last {} instructions", history.len()), |ui| {
+		if history.is_empty() {
+			ui.weak("No instruction was executed");
+			return;
+		}
+
+		egui::ScrollArea::vertical()
+			.max_height(200.0)
+			.stick_to_bottom(true)
+			.show(ui, |ui| {
+				for record in history {
+					let registers: Vec<String> = record
+						.v_registers
+						.iter()
+						.map(|value| format!("{:02X}", value))
+						.collect();
+
+					ui.label(
+						RichText::new(format!(
+							"{:5} {:03X}  {:04X}  {:16} V: {}  I: {:03X}",
+							record.frame,
+							record.pc,
+							record.opcode,
+							record.line().to_string(),
+							registers.join(" "),
+							record.i_register
+						))
+						.monospace(),
+					);
+				}
+			});
+	});
+}
@@ -0,0 +1,205 @@
+use ch8_core::disassembler;
+use ch8_core::{CoreState, Event};
+use eframe::egui;
+use egui::{Color32, RichText};
+use log::warn;
+
+///Number of disassembled lines shown
+const LINES: usize = 32;
+///Lines shown above the followed address
+const LINES_BEFORE: usize = 8;
+
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(0xD0, 0x30, 0x30);
+
+///Disassembly around the program counter with breakpoints
+pub struct DisassemblyView {
+	pub open: bool,
+	follow_pc: bool,
+	///Address shown when the program counter is not followed
+	address: u16,
+	goto: String,
+	///Scrolled distance that has not moved the view by a whole line yet
+	scroll: f32,
+}
+
+impl DisassemblyView {
+	pub fn new() -> Self {
+		Self {
+			open: false,
+			follow_pc: true,
+			address: ch8_core::ROM_ADDRESS as u16,
+			goto: String::new(),
+			scroll: 0.0,
+		}
+	}
+
+	///Returns the events that change breakpoints
+	pub fn show(&mut self, ctx: &egui::Context, state: &CoreState) -> Vec<Event> {
+		let mut events = vec![];
+		if !self.open {
+			return events;
+		}
+
+		let mut open = self.open;
+
+		egui::Window::new("Disassembly")
+			.open(&mut open)
+			.default_width(320.0)
+			.show(ctx, |ui| {
+				self.show_navigation(ui, state);
+
+				ui.separator();
+
+				if self.follow_pc {
+					self.address = state.program_counter;
+				}
+
+				//Keep the alignment of the followed address, which is odd if a ROM jumped into the middle of an instruction
+				let lines_before = (self.address as usize / 2).min(LINES_BEFORE);
+				let start = self.address - lines_before as u16 * 2;
+
+				let response = ui
+					.vertical(|ui| {
+						for line in disassembler::disassemble(&state.memory, start, LINES) {
+							if let Some(event) = self.show_line(ui, state, &line) {
+								events.push(event);
+							}
+						}
+					})
+					.response;
+
+				if response.hovered() {
+					self.handle_scroll(ui);
+				}
+			});
+
+		self.open = open;
+
+		events
+	}
+
+	fn show_navigation(&mut self, ui: &mut egui::Ui, state: &CoreState) {
+		ui.horizontal(|ui| {
+			ui.checkbox(&mut self.follow_pc, "Follow PC");
+
+			let response = ui.add(
+				egui::TextEdit::singleline(&mut self.goto)
+					.hint_text("Address (hex)")
+					.desired_width(80.0),
+			);
+			let entered = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+			if ui.button("Go").clicked() || entered {
+				let address = self.goto.trim().trim_start_matches("0x");
+
+				match u16::from_str_radix(address, 16) {
+					Ok(address) if address < 4096 => self.show_address(address),
+					_ => warn!("Invalid address: {}", self.goto),
+				}
+			}
+
+			if ui
+				.button("Shift alignment")
+				.on_hover_text("Disassemble from the byte after the current address")
+				.clicked()
+			{
+				self.show_address((self.address + 1) % 4096);
+			}
+		});
+
+		if state.program_counter % 2 == 1 {
+			ui.colored_label(
+				ui.visuals().warn_fg_color,
+				"PC is at an odd address, the ROM jumped into the middle of an instruction",
+			);
+		}
+	}
+
+	fn show_line(
+		&mut self,
+		ui: &mut egui::Ui,
+		state: &CoreState,
+		line: &disassembler::Line,
+	) -> Option<Event> {
+		let mut event = None;
+		let breakpoint = state.breakpoints.contains(&line.address);
+		let current = line.address == state.program_counter;
+
+		ui.horizontal(|ui| {
+			let size = ui.text_style_height(&egui::TextStyle::Monospace);
+			let (rect, response) =
+				ui.allocate_exact_size(egui::Vec2::splat(size), egui::Sense::click());
+
+			if breakpoint {
+				ui.painter()
+					.circle_filled(rect.center(), size / 3.0, BREAKPOINT_COLOR);
+			} else if response.hovered() {
+				ui.painter().circle_stroke(
+					rect.center(),
+					size / 3.0,
+					egui::Stroke::new(1.0, BREAKPOINT_COLOR),
+				);
+			}
+
+			if response.on_hover_text("Toggle breakpoint").clicked() {
+				event = Some(Event::ChangeBreakpoint {
+					address: line.address,
+					enabled: !breakpoint,
+				});
+			}
+
+			let marker = if current { "▶" } else { " " };
+			let text = format!(
+				"{} {:03X}  {:04X}  {}",
+				marker, line.address, line.opcode, line
+			);
+			let mut text = RichText::new(text).monospace();
+			if current {
+				text = text
+					.strong()
+					.background_color(ui.visuals().selection.bg_fill);
+			}
+			ui.label(text);
+
+			if let Some(target) = line
+				.instruction
+				.and_then(|instruction| instruction.target())
+			{
+				if ui
+					.link(RichText::new(format!("→ {:03X}", target)).monospace())
+					.on_hover_text("Show target")
+					.clicked()
+				{
+					self.show_address(target);
+				}
+			}
+		});
+
+		event
+	}
+
+	fn show_address(&mut self, address: u16) {
+		self.follow_pc = false;
+		self.address = address;
+	}
+
+	fn handle_scroll(&mut self, ui: &egui::Ui) {
+		let scroll = ui.input().scroll_delta.y;
+		if scroll == 0.0 {
+			return;
+		}
+
+		let line_height = ui.text_style_height(&egui::TextStyle::Monospace);
+		self.scroll += scroll;
+
+		let lines = (self.scroll / line_height).trunc();
+		if lines != 0.0 {
+			self.scroll -= lines * line_height;
+
+			//Scrolling up moves to lower addresses
+			let address = self.address as i32 - lines as i32 * 2;
+			let parity = self.address as i32 % 2;
+			self.show_address(address.clamp(parity, 4094 + parity) as u16);
+		}
+	}
+}
@@ -0,0 +1,285 @@
+use crate::settings::RomProfile;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fmt::Formatter;
+
+///How far a stick has to be pushed before it counts as pressed
+const AXIS_THRESHOLD: f32 = 0.5;
+
+///Button or stick direction of a controller that a CHIP-8 key can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GamepadBind {
+	Button(Button),
+	///Axis pushed past the threshold in the positive or negative direction
+	Axis {
+		axis: Axis,
+		positive: bool,
+	},
+}
+
+impl GamepadBind {
+	pub fn down(&self, controller: &impl Controller) -> bool {
+		match *self {
+			GamepadBind::Button(button) => controller.is_pressed(button),
+			GamepadBind::Axis { axis, positive } => {
+				let value = controller.value(axis);
+
+				if positive {
+					value > AXIS_THRESHOLD
+				} else {
+					value < -AXIS_THRESHOLD
+				}
+			}
+		}
+	}
+}
+
+impl fmt::Display for GamepadBind {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			GamepadBind::Button(button) => write!(f, "{:?}", button),
+			GamepadBind::Axis { axis, positive } => {
+				write!(f, "{:?}{}", axis, if *positive { "+" } else { "-" })
+			}
+		}
+	}
+}
+
+pub type GamepadMap = [Option<GamepadBind>; 16];
+
+pub const DEFAULT_GAMEPAD_MAP: GamepadMap = {
+	use Button::*;
+	use GamepadBind::Button as B;
+
+	[
+		None,               //0
+		Some(B(West)),      //1
+		Some(B(DPadUp)),    //2
+		Some(B(North)),     //3
+		Some(B(DPadLeft)),  //4
+		Some(B(South)),     //5
+		Some(B(DPadRight)), //6
+		None,               //7
+		Some(B(DPadDown)),  //8
+		None,               //9
+		None,               //A
+		None,               //B
+		Some(B(East)),      //C
+		None,               //D
+		Some(B(Select)),    //E
+		Some(B(Start)),     //F
+	]
+};
+
+///Button and axis state of a controller
+pub trait Controller {
+	fn is_pressed(&self, button: Button) -> bool;
+	///Value of `axis` in the range -1.0 to 1.0
+	fn value(&self, axis: Axis) -> f32;
+}
+
+impl Controller for gilrs::Gamepad<'_> {
+	fn is_pressed(&self, button: Button) -> bool {
+		gilrs::Gamepad::is_pressed(self, button)
+	}
+
+	fn value(&self, axis: Axis) -> f32 {
+		gilrs::Gamepad::value(self, axis)
+	}
+}
+
+///CHIP-8 keys that are held down on `controller` according to `map`
+pub fn keys_down(map: &GamepadMap, controller: &impl Controller) -> [bool; 16] {
+	map.map(|bind| bind.is_some_and(|bind| bind.down(controller)))
+}
+
+///Gamepad map of the ROM profile if it has one, otherwise `global`
+pub fn effective_map(global: GamepadMap, profile: Option<&RomProfile>) -> GamepadMap {
+	profile
+		.and_then(|profile| profile.gamepad_map)
+		.unwrap_or(global)
+}
+
+///Controllers connected through gilrs
+pub struct Gamepads {
+	gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+	pub fn new() -> Self {
+		let gilrs = match Gilrs::new() {
+			Ok(gilrs) => Some(gilrs),
+			Err(gilrs::Error::NotImplemented(gilrs)) => {
+				warn!("Gamepads are not supported on this platform");
+				Some(gilrs)
+			}
+			Err(e) => {
+				warn!(
+					"Error initialising gamepad support, disabling gamepads: {}",
+					e
+				);
+				None
+			}
+		};
+
+		Self { gilrs }
+	}
+
+	///Handle pending controller events so the button and axis state is up to date.
+	///Returns the buttons pressed and sticks pushed since the last update, in the order they happened.
+	pub fn update(&mut self) -> Vec<GamepadBind> {
+		let mut pressed = vec![];
+		let gilrs = match self.gilrs.as_mut() {
+			Some(gilrs) => gilrs,
+			None => return pressed,
+		};
+
+		while let Some(event) = gilrs.next_event() {
+			let bind = match event.event {
+				EventType::ButtonPressed(button, _) if button != Button::Unknown => {
+					Some(GamepadBind::Button(button))
+				}
+				EventType::AxisChanged(axis, value, _)
+					if axis != Axis::Unknown && value.abs() > AXIS_THRESHOLD =>
+				{
+					Some(GamepadBind::Axis {
+						axis,
+						positive: value > 0.0,
+					})
+				}
+				EventType::Connected | EventType::Disconnected => {
+					trace!("Gamepad {}: {:?}", event.id, event.event);
+					None
+				}
+				_ => None,
+			};
+
+			pressed.extend(bind);
+		}
+
+		pressed
+	}
+
+	pub fn names(&self) -> Vec<String> {
+		match &self.gilrs {
+			Some(gilrs) => gilrs
+				.gamepads()
+				.map(|(_, gamepad)| gamepad.name().to_string())
+				.collect(),
+			None => vec![],
+		}
+	}
+
+	///CHIP-8 keys held down on any connected controller
+	pub fn keys_down(&self, map: &GamepadMap) -> [bool; 16] {
+		let mut keys = [false; 16];
+
+		if let Some(gilrs) = &self.gilrs {
+			for (_, gamepad) in gilrs.gamepads() {
+				for (key, down) in keys_down(map, &gamepad).into_iter().enumerate() {
+					keys[key] |= down;
+				}
+			}
+		}
+
+		keys
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::{HashMap, HashSet};
+
+	///Controller whose state is set by hand, to check mappings without real hardware
+	#[derive(Default)]
+	struct VirtualController {
+		pressed: HashSet<Button>,
+		axes: HashMap<Axis, f32>,
+	}
+
+	impl Controller for VirtualController {
+		fn is_pressed(&self, button: Button) -> bool {
+			self.pressed.contains(&button)
+		}
+
+		fn value(&self, axis: Axis) -> f32 {
+			self.axes.get(&axis).copied().unwrap_or(0.0)
+		}
+	}
+
+	fn held(keys: [bool; 16]) -> Vec<usize> {
+		(0..16).filter(|key| keys[*key]).collect()
+	}
+
+	#[test]
+	fn button_binds() {
+		let mut controller = VirtualController::default();
+		assert!(!GamepadBind::Button(Button::South).down(&controller));
+		assert!(held(keys_down(&DEFAULT_GAMEPAD_MAP, &controller)).is_empty());
+
+		controller.pressed.insert(Button::South);
+		controller.pressed.insert(Button::Start);
+		assert!(GamepadBind::Button(Button::South).down(&controller));
+		assert_eq!(
+			held(keys_down(&DEFAULT_GAMEPAD_MAP, &controller)),
+			[0x5, 0xF]
+		);
+	}
+
+	#[test]
+	fn stick_directions() {
+		let left = GamepadBind::Axis {
+			axis: Axis::LeftStickX,
+			positive: false,
+		};
+		let right = GamepadBind::Axis {
+			axis: Axis::LeftStickX,
+			positive: true,
+		};
+		let mut map: GamepadMap = [None; 16];
+		map[0x4] = Some(left);
+		map[0x6] = Some(right);
+
+		let mut controller = VirtualController::default();
+		for (value, expected) in [
+			(0.0, vec![]),
+			(AXIS_THRESHOLD, vec![]),
+			(-AXIS_THRESHOLD, vec![]),
+			(0.8, vec![0x6]),
+			(-0.8, vec![0x4]),
+		] {
+			controller.axes.insert(Axis::LeftStickX, value);
+			assert_eq!(held(keys_down(&map, &controller)), expected, "{}", value);
+		}
+
+		assert!(left.down(&controller));
+		assert!(!right.down(&controller));
+	}
+
+	#[test]
+	fn rom_maps() {
+		let mut rom_map: GamepadMap = [None; 16];
+		rom_map[0x1] = Some(GamepadBind::Button(Button::South));
+
+		let mut controller = VirtualController::default();
+		controller.pressed.insert(Button::South);
+
+		let global = effective_map(DEFAULT_GAMEPAD_MAP, None);
+		assert_eq!(held(keys_down(&global, &controller)), [0x5]);
+
+		//Profiles without a gamepad map use the global one
+		let profile = RomProfile::default();
+		let map = effective_map(DEFAULT_GAMEPAD_MAP, Some(&profile));
+		assert_eq!(held(keys_down(&map, &controller)), [0x5]);
+
+		let profile = RomProfile {
+			gamepad_map: Some(rom_map),
+			..RomProfile::default()
+		};
+		let map = effective_map(DEFAULT_GAMEPAD_MAP, Some(&profile));
+		assert_eq!(held(keys_down(&map, &controller)), [0x1]);
+	}
+}
@@ -1,46 +1,113 @@
-use ch8_core::{pixel_buf::PixelBuf, Chip8Core, CoreState};
+use crate::coverage_view::{CoverageAction, CoverageView};
+use crate::debugger;
+use crate::debugger::{RegisterInspector, TraceAction, TraceControls};
+use crate::disassembly::DisassemblyView;
+use crate::gamepad;
+use crate::gamepad::{GamepadBind, GamepadMap, Gamepads};
+use crate::hotkeys::{Hotkey, HotkeyMap};
+use crate::keymap;
+use crate::keymap::{Keymap, KeymapPreset};
+use crate::keypad;
+use crate::library::Library;
+use crate::memory_viewer::MemoryViewer;
+use crate::profiler_view::{ProfilerAction, ProfilerView};
+use crate::settings::{RomProfile, Settings};
+use crate::sprite_viewer::SpriteViewer;
+use ch8_core::coverage;
+use ch8_core::database;
+use ch8_core::database::RomMetadata;
+use ch8_core::display_filter::{DisplayFilter, FrameBlender};
+use ch8_core::framebuffer::Framebuffer;
+use ch8_core::palette::Palette;
+use ch8_core::profiler;
+use ch8_core::quirks;
+use ch8_core::quirks::Quirks;
+use ch8_core::report;
+use ch8_core::report::CrashReport;
+use ch8_core::rom;
+use ch8_core::rom::FileKind;
+use ch8_core::save_state;
+use ch8_core::save_state::SaveState;
+use ch8_core::trace;
+use ch8_core::trace::{TraceFilter, TraceRecorder};
+use ch8_core::upscale::Upscaler;
+use ch8_core::{Chip8Core, CoreState, ErrorKind};
 use eframe::egui::Context;
 use eframe::{egui, CreationContext, Frame};
 use egui::{RichText, Widget};
 use egui_bind::{Bind, BindTarget};
 use egui_dnd::DragDropUi;
 use log::{error, trace, warn};
-use std::thread;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, thread};
 
 const FONT_SIZE: f32 = 1.3;
-const DEFAULT_SCALE: f32 = 4.0;
-
-const DEFAULT_KEYMAP: [Option<(egui_bind::KeyOrPointer, egui::Modifiers)>; 16] = {
-	use egui::Key::*;
-	use egui::Modifiers;
-	use egui_bind::KeyOrPointer;
-
-	[
-		Some((KeyOrPointer::Key(X), Modifiers::NONE)),    //0
-		Some((KeyOrPointer::Key(Num1), Modifiers::NONE)), //1
-		Some((KeyOrPointer::Key(Num2), Modifiers::NONE)), //2
-		Some((KeyOrPointer::Key(Num3), Modifiers::NONE)), //3
-		Some((KeyOrPointer::Key(Q), Modifiers::NONE)),    //4
-		Some((KeyOrPointer::Key(W), Modifiers::NONE)),    //5
-		Some((KeyOrPointer::Key(E), Modifiers::NONE)),    //6
-		Some((KeyOrPointer::Key(A), Modifiers::NONE)),    //7
-		Some((KeyOrPointer::Key(S), Modifiers::NONE)),    //8
-		Some((KeyOrPointer::Key(D), Modifiers::NONE)),    //9
-		Some((KeyOrPointer::Key(Y), Modifiers::NONE)),    //A
-		Some((KeyOrPointer::Key(C), Modifiers::NONE)),    //B
-		Some((KeyOrPointer::Key(Num4), Modifiers::NONE)), //C
-		Some((KeyOrPointer::Key(R), Modifiers::NONE)),    //D
-		Some((KeyOrPointer::Key(F), Modifiers::NONE)),    //E
-		Some((KeyOrPointer::Key(V), Modifiers::NONE)),    //F
-	]
-};
-
-#[derive(Hash, Clone)]
-enum SideMenuSection {
+pub const DEFAULT_SCALE: f32 = 4.0;
+///Pixels per byte in exported memory maps
+const COVERAGE_IMAGE_SCALE: usize = 8;
+///Time without size changes after which resizing the window is considered finished
+const SNAP_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SideMenuSection {
 	Rom,
 	Options,
+	Display,
 	Info,
 	Keymap,
+	Hotkeys,
+	Debugger,
+}
+
+impl SideMenuSection {
+	pub const ALL: [SideMenuSection; 7] = [
+		SideMenuSection::Rom,
+		SideMenuSection::Options,
+		SideMenuSection::Display,
+		SideMenuSection::Info,
+		SideMenuSection::Keymap,
+		SideMenuSection::Hotkeys,
+		SideMenuSection::Debugger,
+	];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleMode {
+	///Largest scale that fits the window
+	Fit,
+	///Largest whole-number scale that fits the window, centred with letterboxing
+	Integer,
+	///Fill the whole window, ignoring the aspect ratio
+	Stretch,
+}
+
+impl ScaleMode {
+	const ALL: [ScaleMode; 3] = [ScaleMode::Fit, ScaleMode::Integer, ScaleMode::Stretch];
+
+	fn next(self) -> Self {
+		match self {
+			ScaleMode::Fit => ScaleMode::Integer,
+			ScaleMode::Integer => ScaleMode::Stretch,
+			ScaleMode::Stretch => ScaleMode::Fit,
+		}
+	}
+}
+
+impl fmt::Display for ScaleMode {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			ScaleMode::Fit => write!(f, "Fit"),
+			ScaleMode::Integer => write!(f, "Integer"),
+			ScaleMode::Stretch => write!(f, "Stretch to fill"),
+		}
+	}
 }
 
 #[derive(Hash, Clone)]
@@ -48,6 +115,8 @@ struct SideMenuDragDropItem(SideMenuSection);
 
 pub struct Gui {
 	theme: eframe::Theme,
+	///Whether the user picked `theme`, otherwise it follows the system theme
+	theme_picked: bool,
 	first_frame: bool,
 	scale: f32,
 	max_scale: f32,
@@ -55,30 +124,80 @@ pub struct Gui {
 	frame_no_margin: egui::containers::Frame,
 	emu_core: Chip8Core,
 	gui_error: Option<String>,
+	///Error of the loaded crash report, the core itself is paused without an error
+	crash_report_error: Option<ErrorKind>,
 	last_rom_path: Option<std::path::PathBuf>,
 	side_menu_width: f32,
 	side_menu_sections: Vec<SideMenuDragDropItem>,
 	side_menu_drag_state: DragDropUi,
 	scale_locked: bool,
-	keymap: [Option<(egui_bind::KeyOrPointer, egui::Modifiers)>; 16],
+	///Last time the scale changed by resizing the window, until the window is snapped to the scale
+	resized_at: Option<Instant>,
+	///Global defaults, used when neither the ROM profile nor the ROM database set a value
+	opcodes_per_frame: u32,
+	quirks: Quirks,
+	keymap: Keymap,
+	gamepad_map: GamepadMap,
+	gamepads: Gamepads,
+	///CHIP-8 key that the next pressed controller button is bound to
+	gamepad_binding: Option<usize>,
+	///Controller buttons pressed since the last GUI frame
+	gamepad_pressed: Vec<GamepadBind>,
+	///Keys held down on the on-screen keypad
+	keypad_keys: [bool; 16],
+	///Keys down according to the last key events sent to the core
+	keys_sent: [bool; 16],
+	hotkeys: HotkeyMap,
+	palette: Palette,
+	rom_profiles: HashMap<String, RomProfile>,
+	frame_blender: FrameBlender,
+	blended_frame: Option<u64>,
+	upscaler: Upscaler,
+	screenshot_scale: usize,
+	screenshot_directory: Option<PathBuf>,
+	scale_mode: ScaleMode,
+	fullscreen: bool,
+	side_menu_visible: bool,
+	restored_scale: Option<f32>,
+	resume_last_rom: bool,
+	///File given on the command line, opened instead of resuming the last ROM
+	startup_file: Option<PathBuf>,
+	library: Library,
+	memory_viewer: MemoryViewer,
+	register_inspector: RegisterInspector,
+	trace_controls: TraceControls,
+	disassembly: DisassemblyView,
+	sprite_viewer: SpriteViewer,
+	profiler_view: ProfilerView,
+	coverage_view: CoverageView,
 }
 
 impl Gui {
-	pub fn new(cc: &CreationContext) -> Self {
+	pub fn new(cc: &CreationContext, startup_file: Option<PathBuf>) -> Self {
 		let egui_ctx = cc.egui_ctx.clone();
 		let emu_core = ch8_core::create_and_run(Box::new(move || {
 			egui_ctx.request_repaint();
 		}));
 
-		let theme = cc
-			.integration_info
-			.system_theme
-			.unwrap_or(eframe::Theme::Dark);
+		let mut settings: Settings = cc
+			.storage
+			.and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+			.unwrap_or_default();
+		settings.migrate();
+
+		let theme = match settings.dark_theme {
+			Some(true) => eframe::Theme::Dark,
+			Some(false) => eframe::Theme::Light,
+			None => cc
+				.integration_info
+				.system_theme
+				.unwrap_or(eframe::Theme::Dark),
+		};
 		trace!("Theme: {:?}", theme);
 
-		use SideMenuSection::*;
-		Gui {
+		let mut gui = Gui {
 			theme,
+			theme_picked: settings.dark_theme.is_some(),
 			first_frame: true,
 			scale: 0.0,
 			max_scale: 0.0,
@@ -86,23 +205,181 @@ impl Gui {
 			frame_no_margin: egui::containers::Frame::default(),
 			emu_core,
 			gui_error: None,
-			last_rom_path: None,
+			crash_report_error: None,
+			last_rom_path: settings.last_rom_path.clone(),
 			side_menu_width: 0.0,
-			side_menu_sections: vec![
-				SideMenuDragDropItem(Rom),
-				SideMenuDragDropItem(Options),
-				SideMenuDragDropItem(Info),
-				SideMenuDragDropItem(Keymap),
-			],
+			side_menu_sections: settings
+				.side_menu_sections()
+				.into_iter()
+				.map(SideMenuDragDropItem)
+				.collect(),
 			side_menu_drag_state: DragDropUi::default(),
 			scale_locked: false,
-			keymap: DEFAULT_KEYMAP,
+			resized_at: None,
+			opcodes_per_frame: settings.opcodes_per_frame,
+			quirks: settings.quirks,
+			keymap: settings.keymap(),
+			gamepad_map: settings.gamepad_map,
+			hotkeys: HotkeyMap::from_saved(&settings.hotkeys),
+			gamepads: Gamepads::new(),
+			gamepad_binding: None,
+			gamepad_pressed: vec![],
+			keypad_keys: [false; 16],
+			keys_sent: [false; 16],
+			palette: settings.palette,
+			rom_profiles: settings.rom_profiles.clone(),
+			frame_blender: FrameBlender::new(settings.display_filter),
+			blended_frame: None,
+			upscaler: settings.upscaler,
+			screenshot_scale: settings.screenshot_scale,
+			screenshot_directory: settings.screenshot_directory.clone(),
+			scale_mode: settings.scale_mode,
+			fullscreen: false,
+			side_menu_visible: settings.side_menu_visible,
+			restored_scale: settings.scale,
+			resume_last_rom: settings.resume_last_rom,
+			startup_file,
+			library: Library::new(
+				settings.library_directories.clone(),
+				settings.recent_roms.clone(),
+			),
+			memory_viewer: MemoryViewer::default(),
+			register_inspector: RegisterInspector::default(),
+			trace_controls: TraceControls::default(),
+			disassembly: DisassemblyView::new(),
+			sprite_viewer: SpriteViewer::new(),
+			profiler_view: ProfilerView::new(),
+			coverage_view: CoverageView::new(),
+		};
+
+		gui.send_event(ch8_core::Event::ChangeOpcodesPerFrame(
+			settings.opcodes_per_frame,
+		));
+		gui.send_event(ch8_core::Event::ChangeQuirks(settings.quirks));
+
+		gui
+	}
+
+	fn settings(&mut self) -> Settings {
+		let keymap = keymap::to_saved(&self.keymap);
+
+		Settings {
+			dark_theme: self
+				.theme_picked
+				.then_some(self.theme == eframe::Theme::Dark),
+			scale: Some(self.scale),
+			scale_mode: self.scale_mode,
+			opcodes_per_frame: self.opcodes_per_frame,
+			quirks: self.quirks,
+			keymap: keymap.map(|binds| binds[0]),
+			secondary_keymap: keymap.map(|binds| binds[1]),
+			gamepad_map: self.gamepad_map,
+			hotkeys: self.hotkeys.to_saved(),
+			side_menu_sections: self.side_menu_sections.iter().map(|item| item.0).collect(),
+			side_menu_visible: self.side_menu_visible,
+			palette: self.palette,
+			rom_profiles: self.rom_profiles.clone(),
+			rom_palettes: HashMap::new(),
+			display_filter: self.frame_blender.filter(),
+			upscaler: self.upscaler,
+			screenshot_scale: self.screenshot_scale,
+			screenshot_directory: self.screenshot_directory.clone(),
+			last_rom_path: self.last_rom_path.clone(),
+			resume_last_rom: self.resume_last_rom,
+			library_directories: self.library.directories.clone(),
+			recent_roms: self.library.recent.clone(),
 		}
 	}
 
 	fn setup(&mut self, ctx: &Context, frame: &mut Frame) {
 		self.update_theme(ctx);
+		self.update_frames(ctx);
+
+		if FONT_SIZE != 1.0 {
+			let mut style = (*ctx.style()).clone();
+
+			for text_style in style.text_styles.iter_mut() {
+				text_style.1.size *= FONT_SIZE;
+			}
+
+			ctx.set_style(style);
+		}
+
+		self.setup_window(frame);
+
+		if let Some(path) = self.startup_file.take() {
+			trace!("Opening {} from the command line", path.display());
+			self.open_file(ctx, path);
+		} else if self.resume_last_rom {
+			if let Some(path) = self.last_rom_path.clone() {
+				if path.exists() {
+					trace!("Resuming last ROM: {}", path.display());
+					self.open_file(ctx, path.clone());
+					self.resume_auto_save(&path);
+				} else {
+					warn!("Last ROM {} does not exist anymore", path.display());
+				}
+			}
+		}
+	}
+
+	///Continue the ROM at `rom` from its auto-save, if there is one of the same ROM
+	fn resume_auto_save(&mut self, rom: &Path) {
+		let path = save_state::auto_save_path(rom);
+		if !path.exists() {
+			return;
+		}
+
+		let save_state = match SaveState::load(&path) {
+			Ok(save_state) => save_state,
+			Err(e) => {
+				warn!("Cannot resume auto-save {}: {}", path.display(), e);
+				return;
+			}
+		};
+
+		let rom_hash = fs::read(rom).ok().map(|rom| database::hash(&rom));
+		if save_state.rom_hash.is_none() || save_state.rom_hash != rom_hash {
+			warn!(
+				"Ignoring auto-save {}, it belongs to a different ROM",
+				path.display()
+			);
+			return;
+		}
+
+		trace!("Resuming auto-save {}", path.display());
+		self.send_event(ch8_core::Event::LoadSaveState {
+			save_state: Box::new(save_state),
+			path: save_state::path(rom),
+		});
+	}
+
+	///Save the state of the last ROM, so it can be resumed on the next launch
+	fn auto_save(&mut self) {
+		let path = match &self.last_rom_path {
+			Some(rom) => save_state::auto_save_path(rom),
+			None => return,
+		};
+
+		//A save state opened since the ROM was loaded must not overwrite the ROM's auto-save
+		let rom_hash = self
+			.last_rom_path
+			.as_deref()
+			.and_then(|rom| fs::read(rom).ok())
+			.map(|rom| database::hash(&rom));
+
+		let core = self.core();
+		if core.rom_hash.is_none() || core.rom_hash != rom_hash || core.error.is_some() {
+			return;
+		}
+
+		match SaveState::new(core).save(&path) {
+			Ok(()) => trace!("Auto-saved to {}", path.display()),
+			Err(e) => warn!("Error writing auto-save {}: {}", path.display(), e),
+		}
+	}
 
+	fn update_frames(&mut self, ctx: &Context) {
 		self.transparent_frame = {
 			let mut transparent_frame = egui::Frame::window(&ctx.style());
 			let fill = egui::Color32::from_rgba_unmultiplied(
@@ -119,18 +396,6 @@ impl Gui {
 		self.frame_no_margin = egui::Frame::window(&ctx.style())
 			.inner_margin(0.0)
 			.shadow(egui::epaint::Shadow::NONE);
-
-		if FONT_SIZE != 1.0 {
-			let mut style = (*ctx.style()).clone();
-
-			for text_style in style.text_styles.iter_mut() {
-				text_style.1.size *= FONT_SIZE;
-			}
-
-			ctx.set_style(style);
-		}
-
-		self.setup_window(frame);
 	}
 
 	fn get_monitor_size(frame: &Frame) -> (f32, f32) {
@@ -153,6 +418,20 @@ impl Gui {
 
 		//Add 30% so max scale cannot be reached by resizing the window
 		self.max_scale = (screen_width / ch8_core::WIDTH as f32).round() * 1.3;
+
+		if let Some(scale) = self.restored_scale {
+			//The window position was restored by eframe, only the size has to be restored
+			self.scale = scale.min(self.max_scale);
+			trace!(
+				"Max scale: {}, restored scale: {}",
+				self.max_scale,
+				self.scale
+			);
+
+			self.resize_to_scale(frame);
+			return;
+		}
+
 		self.scale = (self.max_scale / 1.8).round();
 		trace!("Max scale: {}, scale: {}", self.max_scale, self.scale);
 
@@ -189,12 +468,11 @@ impl Gui {
 		}
 	}
 
-	fn latest_frame(&mut self) -> &PixelBuf {
+	fn latest_frame(&mut self) -> &Framebuffer {
 		&self.core().image
 	}
 
 	fn resize_to_scale(&mut self, frame: &mut Frame) {
-		//TODO Snap to scale after resizing the window
 		let scaled_size = {
 			let scale = self.scale;
 			self.latest_frame().get_scaled_size(scale)
@@ -222,10 +500,37 @@ impl Gui {
 
 		if self.scale != new_scale {
 			self.scale = new_scale;
+			self.resized_at = Some(Instant::now());
 			trace!("New scale: {}", self.scale);
 		}
 	}
 
+	///In integer scale mode, shrink the window to the whole-number scale once resizing it stopped
+	fn snap_to_scale(&mut self, ctx: &Context, frame: &mut Frame) {
+		let resized_at = match self.resized_at {
+			Some(resized_at) if self.scale_mode == ScaleMode::Integer => resized_at,
+			_ => {
+				self.resized_at = None;
+				return;
+			}
+		};
+
+		let elapsed = resized_at.elapsed();
+		if elapsed < SNAP_DELAY {
+			ctx.request_repaint_after(SNAP_DELAY - elapsed);
+			return;
+		}
+		self.resized_at = None;
+
+		//Rounding errors of the window size must not shrink a window that already has the right size
+		if (self.scale - self.scale.round()).abs() < 0.01 {
+			return;
+		}
+
+		self.scale = self.scale.floor().max(1.0);
+		self.resize_to_scale(frame);
+	}
+
 	fn add_side_menu(&mut self, ctx: &Context, frame: &mut Frame) {
 		let side_menu = egui::SidePanel::right("side_menu")
 			.exact_width(400.0)
@@ -237,7 +542,7 @@ impl Gui {
 		self.side_menu_width = side_menu.response.rect.size().x;
 	}
 
-	fn show_side_menu_sections(&mut self, ctx: &Context, _frame: &mut Frame, ui: &mut egui::Ui) {
+	fn show_side_menu_sections(&mut self, ctx: &Context, frame: &mut Frame, ui: &mut egui::Ui) {
 		let mut drag_state = self.side_menu_drag_state.clone();
 
 		ui.add_space(10.0);
@@ -260,12 +565,21 @@ impl Gui {
 						Options => {
 							self.show_options_section(ctx, ui);
 						}
+						Display => {
+							self.show_display_section(ctx, frame, ui);
+						}
 						Rom => {
 							self.show_rom_section(ctx, ui);
 						}
 						Keymap => {
 							self.show_keymap_section(ui);
 						}
+						Hotkeys => {
+							self.show_hotkeys_section(ui);
+						}
+						Debugger => {
+							self.show_debugger_section(ui);
+						}
 					}
 				});
 
@@ -302,25 +616,45 @@ impl Gui {
 						}
 					};
 
-					ui.label(format!("Rom name: {}", rom_name));
+					let rom_hash = core.rom_hash.clone();
+					let rom_metadata = core.rom_metadata.clone();
+
+					let name_label = ui.label(format!("Rom name: {}", rom_name));
+					if let Some(rom_hash) = rom_hash {
+						name_label.on_hover_text(format!("SHA-1: {}", rom_hash));
+					}
 					ui.label(format!("Rom size: {}", rom_size));
 
+					let kind = core
+						.rom_name
+						.as_deref()
+						.and_then(|name| FileKind::detect(Path::new(name)));
+					if let Some(kind @ (FileKind::SuperChip | FileKind::XoChip)) = kind {
+						ui.weak(format!(
+							"{}: only the CHIP-8 instructions are emulated",
+							kind
+						));
+					}
+
+					if let Some(metadata) = rom_metadata {
+						Self::show_rom_metadata(&metadata, ui);
+					}
+
+					ui.checkbox(
+						&mut self.resume_last_rom,
+						"Resume last ROM and its state on startup",
+					);
+
 					if ui.button("Load").clicked() {
-						//TODO Implement dragging the ROM onto the gui
 						let path = rfd::FileDialog::new()
-							.add_filter("CH8 files", &["ch8"])
+							.add_filter("ROM files", &ch8_core::rom::ROM_EXTENSIONS)
+							.add_filter("Save states", &[rom::SAVE_STATE_EXTENSION])
+							.add_filter("Crash reports", &[report::REPORT_EXTENSION])
 							.pick_file();
 
 						if let Some(path) = path {
 							trace!("ROM file picked: {}", path.display());
-							if core.rom_name.is_some() {
-								//Reset core if a rom was already loaded
-								self.reset_core(ctx);
-							}
-
-							self.last_rom_path = Some(path.clone());
-							self.send_event(ch8_core::Event::LoadRom(path));
-							self.send_event(ch8_core::Event::ChangeRunning(true));
+							self.open_file(ctx, path);
 						} else {
 							error!("Error while picking rom file");
 
@@ -328,34 +662,317 @@ impl Gui {
 								Some("Error while picking rom file, please try again".into());
 						}
 					}
+
+					if ui.button("Library").clicked() {
+						self.library.open = !self.library.open;
+					}
+
+					let mut picked_rom = None;
+					ui.collapsing("Recent", |ui| {
+						for path in &self.library.recent {
+							let name = path
+								.file_name()
+								.map(|name| name.to_string_lossy().to_string())
+								.unwrap_or_else(|| path.display().to_string());
+
+							if ui
+								.button(name)
+								.on_hover_text(path.display().to_string())
+								.clicked()
+							{
+								picked_rom = Some(path.clone());
+							}
+						}
+					});
+					if let Some(path) = picked_rom {
+						self.open_file(ctx, path);
+					}
 				});
 			});
 	}
 
+	fn show_rom_metadata(metadata: &RomMetadata, ui: &mut egui::Ui) {
+		let title_label = ui.label(format!("Title: {}", metadata.title));
+		if let Some(description) = &metadata.description {
+			title_label.on_hover_text(description);
+		}
+
+		if !metadata.authors.is_empty() {
+			ui.label(format!("Authors: {}", metadata.authors.join(", ")));
+		}
+		if let Some(release) = &metadata.release {
+			ui.label(format!("Release: {}", release));
+		}
+		if let Some(platform) = &metadata.platform {
+			ui.label(format!("Platform: {}", quirks::platform_name(platform)));
+		}
+
+		if !metadata.keys.is_empty() {
+			ui.collapsing("Key hints", |ui| {
+				for (action, key) in &metadata.keys {
+					ui.label(format!("{:X}: {}", key, action));
+				}
+			});
+		}
+	}
+
+	fn open_file(&mut self, ctx: &Context, path: PathBuf) {
+		let kind = match FileKind::detect(&path) {
+			Some(kind) => kind,
+			None => {
+				error!("Cannot open {}: unknown file type", path.display());
+
+				self.gui_error = Some(format!(
+					"Cannot open '{}': unknown file type, ROMs need the extension .{}",
+					path.display(),
+					ch8_core::rom::ROM_EXTENSIONS.join(", .")
+				));
+				return;
+			}
+		};
+		trace!("Opening {}: {}", kind, path.display());
+
+		match kind {
+			FileKind::Chip8 | FileKind::SuperChip | FileKind::XoChip => self.load_rom(ctx, path),
+			FileKind::OctoSource => {
+				error!("Cannot open {}: {}", kind, path.display());
+
+				self.gui_error = Some(format!(
+					"Cannot open '{}': Octo sources must be exported as a ROM first",
+					path.display()
+				));
+			}
+			FileKind::SaveState => self.load_save_state(ctx, path),
+			FileKind::CrashReport => self.load_crash_report(ctx, path),
+		}
+	}
+
+	///Continue from a save state file, which contains the ROM in its memory
+	fn load_save_state(&mut self, ctx: &Context, path: PathBuf) {
+		let save_state = match SaveState::load(&path) {
+			Ok(save_state) => save_state,
+			Err(e) => {
+				error!("Error loading save state {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error loading save state '{}': {}",
+					path.display(),
+					e
+				));
+				return;
+			}
+		};
+
+		self.reset_core(ctx);
+		self.send_event(ch8_core::Event::LoadSaveState {
+			save_state: Box::new(save_state),
+			path,
+		});
+		self.send_event(ch8_core::Event::ChangeRunning(true));
+	}
+
+	fn load_rom(&mut self, ctx: &Context, path: PathBuf) {
+		if self.core().rom_name.is_some() {
+			//Reset core if a rom was already loaded
+			self.reset_core(ctx);
+		}
+
+		self.last_rom_path = Some(path.clone());
+		self.library.add_recent(path.clone());
+		self.start_rom(path);
+	}
+
+	///Load the ROM into the core, apply its settings profile and start running
+	fn start_rom(&mut self, path: PathBuf) {
+		//Reading errors are reported by the core when it loads the ROM
+		let hash = fs::read(&path).ok().map(|rom| database::hash(&rom));
+		let profile = hash
+			.as_ref()
+			.and_then(|hash| self.rom_profiles.get(hash).cloned());
+		let database_quirks = hash
+			.as_deref()
+			.and_then(database::lookup)
+			.and_then(|metadata| metadata.quirks);
+		let platform_quirks = FileKind::detect(&path).and_then(|kind| kind.quirks());
+
+		//The core applies the ROM database entry when loading,
+		//so the profile has to be sent afterwards to take precedence
+		self.send_event(ch8_core::Event::LoadRom(path));
+
+		//The extension only decides the quirks if the ROM database does not know the ROM
+		if let (None, Some(quirks)) = (database_quirks, platform_quirks) {
+			self.send_event(ch8_core::Event::ChangeQuirks(quirks));
+		}
+
+		if let Some(profile) = profile {
+			if let Some(opcodes_per_frame) = profile.opcodes_per_frame {
+				self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(opcodes_per_frame));
+			}
+			if let Some(quirks) = profile.quirks {
+				self.send_event(ch8_core::Event::ChangeQuirks(quirks));
+			}
+		}
+
+		self.send_event(ch8_core::Event::ChangeRunning(true));
+	}
+
+	///Show the machine state of a crash report paused at the reported error
+	fn load_crash_report(&mut self, ctx: &Context, path: PathBuf) {
+		let report = match CrashReport::load(&path) {
+			Ok(report) => report,
+			Err(e) => {
+				error!("Error loading crash report {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error loading crash report '{}': {}",
+					path.display(),
+					e
+				));
+				return;
+			}
+		};
+
+		trace!("Crash report created {} loaded", report.created);
+		self.reset_core(ctx);
+		self.crash_report_error = Some(report.error.clone());
+		self.send_event(ch8_core::Event::LoadCrashReport(Box::new(report)));
+	}
+
+	fn save_crash_report(&mut self, core: &CoreState) {
+		let settings = match serde_json::to_value(self.settings()) {
+			Ok(settings) => settings,
+			Err(e) => {
+				warn!("Settings cannot be added to the crash report: {}", e);
+				serde_json::Value::Null
+			}
+		};
+
+		let report = match CrashReport::new(core, settings) {
+			Some(report) => report,
+			None => {
+				warn!("No error to report");
+				return;
+			}
+		};
+
+		let path = match rfd::FileDialog::new()
+			.add_filter("Crash report", &[report::REPORT_EXTENSION])
+			.set_file_name(&report::file_name(core.rom_name.as_deref()))
+			.save_file()
+		{
+			Some(path) => path,
+			None => return,
+		};
+
+		match report.save(&path, &self.palette()) {
+			Ok(()) => trace!("Crash report saved to {}", path.display()),
+			Err(e) => {
+				error!("Error saving crash report to {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error saving crash report to '{}': {}",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
+	fn handle_dropped_files(&mut self, ctx: &Context) {
+		let dropped_files = ctx.input().raw.dropped_files.clone();
+
+		if self.error_occurred() {
+			return;
+		}
+
+		//Only open the first file if multiple files are dropped at once
+		if let Some(path) = dropped_files.into_iter().find_map(|file| file.path) {
+			trace!("File dropped: {}", path.display());
+			self.open_file(ctx, path);
+		}
+	}
+
 	fn show_options_section(&mut self, ctx: &Context, ui: &mut egui::Ui) {
 		egui::CollapsingHeader::new("Options")
 			.default_open(true)
 			.show(ui, |ui| {
 				ui.add_enabled_ui(!self.error_occurred(), |ui| {
-					let core = self.core();
+					let profile = self.rom_profile();
+					let overridden = profile
+						.as_ref()
+						.is_some_and(|profile| profile.opcodes_per_frame.is_some());
+
+					if let Some(overridden) = self.show_profile_toggle(ui, overridden) {
+						let opcodes_per_frame = self.core().opcodes_per_frame;
+						let quirks = self.core().quirks;
+						self.update_rom_profile(|profile| {
+							profile.opcodes_per_frame = overridden.then_some(opcodes_per_frame);
+							profile.quirks = overridden.then_some(quirks);
+						});
+
+						if !overridden {
+							let opcodes_per_frame = self.effective_opcodes_per_frame();
+							let quirks = self.effective_quirks();
+							self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(
+								opcodes_per_frame,
+							));
+							self.send_event(ch8_core::Event::ChangeQuirks(quirks));
+						}
+					}
 
-					let mut opcodes_per_frame = core.opcodes_per_frame;
+					let mut opcodes_per_frame = self.core().opcodes_per_frame;
 					let slider = ui.add(
 						egui::Slider::new(&mut opcodes_per_frame, 1..=200)
 							.text("Opcodes per frame"),
 					);
 
-					if slider.changed() {
-						self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(opcodes_per_frame));
-					}
 					if slider.double_clicked() {
-						self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(20));
+						opcodes_per_frame = 20;
+					}
+					if slider.changed() || slider.double_clicked() {
+						if overridden {
+							self.update_rom_profile(|profile| {
+								profile.opcodes_per_frame = Some(opcodes_per_frame)
+							});
+						} else {
+							self.opcodes_per_frame = opcodes_per_frame;
+						}
+						self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(opcodes_per_frame));
 					}
 
+					self.show_quirks_options(ui, overridden);
+
 					self.show_running_and_step_frame(ui);
 
 					ui.separator();
 
+					ui.add(
+						egui::Slider::new(&mut self.screenshot_scale, 1..=16)
+							.text("Screenshot scale"),
+					);
+
+					ui.horizontal(|ui| {
+						let directory = match &self.screenshot_directory {
+							Some(directory) => directory.display().to_string(),
+							None => "Next to ROM".into(),
+						};
+						ui.label(format!("Screenshot folder: {}", directory));
+
+						if ui.button("Change").clicked() {
+							if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+								trace!("Screenshot folder picked: {}", directory.display());
+								self.screenshot_directory = Some(directory);
+							}
+						}
+						if self.screenshot_directory.is_some() && ui.button("Clear").clicked() {
+							self.screenshot_directory = None;
+						}
+					});
+
+					self.show_screenshot_button(ui);
+
+					ui.separator();
+
 					ui.horizontal(|ui| {
 						if ui.button("Reset").clicked() {
 							self.reset_core(ctx);
@@ -368,6 +985,225 @@ impl Gui {
 			});
 	}
 
+	fn show_quirks_options(&mut self, ui: &mut egui::Ui, overridden: bool) {
+		let mut quirks = self.core().quirks;
+
+		let changed = ui
+			.collapsing("Quirks", |ui| {
+				let mut changed = false;
+
+				egui::ComboBox::from_label("Preset")
+					.selected_text("Select")
+					.show_ui(ui, |ui| {
+						for (name, preset) in [
+							("CHIP-8", Quirks::ORIGINAL_CHIP8),
+							("CHIP-8 (modern)", Quirks::MODERN_CHIP8),
+							("CHIP-48", Quirks::CHIP48),
+							("SUPER-CHIP", Quirks::SUPER_CHIP),
+							("XO-CHIP", Quirks::XO_CHIP),
+						] {
+							changed |= ui.selectable_value(&mut quirks, preset, name).changed();
+						}
+					});
+
+				for (quirk, label, description) in [
+					(
+						&mut quirks.logic,
+						"Logic",
+						"8XY1, 8XY2 and 8XY3 reset VF to 0",
+					),
+					(
+						&mut quirks.shift,
+						"Shift",
+						"8XY6 and 8XYE shift VX in place",
+					),
+					(
+						&mut quirks.memory_increment_by_x,
+						"Memory increment by X",
+						"FX55 and FX65 increment I by X instead of X + 1",
+					),
+					(
+						&mut quirks.memory_leave_i_unchanged,
+						"Memory leave I unchanged",
+						"FX55 and FX65 leave I unchanged",
+					),
+					(
+						&mut quirks.wrap,
+						"Wrap",
+						"Sprites wrap around the screen edges",
+					),
+					(&mut quirks.jump, "Jump", "BXNN jumps to XNN plus VX"),
+					(
+						&mut quirks.vblank,
+						"VBlank",
+						"DXYN waits for the vertical blank",
+					),
+					(
+						&mut quirks.key_wait_release,
+						"Key wait release",
+						"FX0A continues when a key is released instead of pressed",
+					),
+				] {
+					changed |= ui
+						.checkbox(quirk, label)
+						.on_hover_text(description)
+						.changed();
+				}
+
+				changed
+			})
+			.body_returned
+			.unwrap_or(false);
+
+		if changed {
+			if overridden {
+				self.update_rom_profile(|profile| profile.quirks = Some(quirks));
+			} else {
+				self.quirks = quirks;
+			}
+			self.send_event(ch8_core::Event::ChangeQuirks(quirks));
+		}
+	}
+
+	fn show_display_section(&mut self, ctx: &Context, frame: &mut Frame, ui: &mut egui::Ui) {
+		egui::CollapsingHeader::new("Display")
+			.default_open(true)
+			.show(ui, |ui| {
+				let theme = self.theme;
+				egui::ComboBox::from_label("Theme")
+					.selected_text(format!("{:?}", self.theme))
+					.show_ui(ui, |ui| {
+						for theme in [eframe::Theme::Dark, eframe::Theme::Light] {
+							ui.selectable_value(&mut self.theme, theme, format!("{:?}", theme));
+						}
+					});
+				if self.theme != theme {
+					self.theme_picked = true;
+					self.update_theme(ctx);
+					self.update_frames(ctx);
+				}
+
+				self.show_palette_options(ui);
+				self.show_display_filter_options(ui);
+
+				ui.separator();
+
+				egui::ComboBox::from_label(self.hotkeys.label(Hotkey::ScaleMode))
+					.selected_text(self.scale_mode.to_string())
+					.show_ui(ui, |ui| {
+						for scale_mode in ScaleMode::ALL {
+							ui.selectable_value(
+								&mut self.scale_mode,
+								scale_mode,
+								scale_mode.to_string(),
+							);
+						}
+					});
+
+				self.show_window_options(frame, ui);
+			});
+	}
+
+	fn show_window_options(&mut self, frame: &mut Frame, ui: &mut egui::Ui) {
+		let mut fullscreen = self.fullscreen;
+		if ui
+			.checkbox(&mut fullscreen, self.hotkeys.label(Hotkey::Fullscreen))
+			.clicked()
+		{
+			self.set_fullscreen(frame, fullscreen);
+			ui.close_menu();
+		}
+
+		let mut side_menu_visible = self.side_menu_visible;
+		if ui
+			.checkbox(&mut side_menu_visible, self.hotkeys.label(Hotkey::SideMenu))
+			.clicked()
+		{
+			self.side_menu_visible = side_menu_visible;
+			ui.close_menu();
+		}
+	}
+
+	fn set_fullscreen(&mut self, frame: &mut Frame, fullscreen: bool) {
+		trace!("Fullscreen: {}", fullscreen);
+
+		self.fullscreen = fullscreen;
+		frame.set_fullscreen(fullscreen);
+	}
+
+	fn show_palette_options(&mut self, ui: &mut egui::Ui) {
+		let overridden = self
+			.rom_profile()
+			.is_some_and(|profile| profile.palette.is_some());
+
+		if let Some(overridden) = self.show_profile_toggle(ui, overridden) {
+			let palette = self.palette();
+			self.update_rom_profile(|profile| profile.palette = overridden.then_some(palette));
+		}
+
+		let mut palette = self.palette();
+		let mut changed = false;
+
+		egui::ComboBox::from_label("Palette")
+			.selected_text(palette.preset_name().unwrap_or("Custom"))
+			.show_ui(ui, |ui| {
+				for (name, preset) in Palette::PRESETS {
+					changed |= ui.selectable_value(&mut palette, preset, name).changed();
+				}
+			});
+
+		ui.horizontal(|ui| {
+			changed |= ui.color_edit_button_srgb(&mut palette.foreground).changed();
+			ui.label("Foreground");
+
+			changed |= ui.color_edit_button_srgb(&mut palette.background).changed();
+			ui.label("Background");
+		});
+
+		if !overridden && self.database_palette().is_some() {
+			ui.label("Using palette from ROM database");
+		}
+
+		if changed {
+			if overridden {
+				self.update_rom_profile(|profile| profile.palette = Some(palette));
+			} else {
+				self.palette = palette;
+			}
+		}
+	}
+
+	fn show_display_filter_options(&mut self, ui: &mut egui::Ui) {
+		let mut filter = self.frame_blender.filter();
+
+		egui::ComboBox::from_label("Display filter")
+			.selected_text(filter.to_string())
+			.show_ui(ui, |ui| {
+				for option in DisplayFilter::ALL {
+					let selected =
+						std::mem::discriminant(&filter) == std::mem::discriminant(&option);
+
+					if ui.selectable_label(selected, option.to_string()).clicked() && !selected {
+						filter = option;
+					}
+				}
+			});
+
+		if let DisplayFilter::Persistence { decay } = &mut filter {
+			ui.add(egui::Slider::new(decay, 0.0..=0.95).text("Decay"));
+		}
+
+		self.frame_blender.set_filter(filter);
+
+		egui::ComboBox::from_label("Upscaler")
+			.selected_text(self.upscaler.to_string())
+			.show_ui(ui, |ui| {
+				for upscaler in Upscaler::ALL {
+					ui.selectable_value(&mut self.upscaler, upscaler, upscaler.to_string());
+				}
+			});
+	}
+
 	fn show_info_section(&mut self, ctx: &Context, ui: &mut egui::Ui) {
 		egui::CollapsingHeader::new("Info")
 			.default_open(true)
@@ -396,77 +1232,414 @@ impl Gui {
 			});
 	}
 
+	fn show_debugger_section(&mut self, ui: &mut egui::Ui) {
+		egui::CollapsingHeader::new("Debugger")
+			.default_open(true)
+			.show(ui, |ui| {
+				ui.add_enabled_ui(self.core().rom_name.is_some(), |ui| {
+					self.show_running_and_step_frame(ui);
+
+					ui.horizontal(|ui| {
+						if ui.button("Memory viewer").clicked() {
+							self.memory_viewer.open = !self.memory_viewer.open;
+						}
+						if ui.button("Disassembly").clicked() {
+							self.disassembly.open = !self.disassembly.open;
+						}
+						if ui.button("Sprite").clicked() {
+							self.sprite_viewer.open = !self.sprite_viewer.open;
+						}
+						if ui.button("Profiler").clicked() {
+							self.profiler_view.open = !self.profiler_view.open;
+						}
+						if ui.button("Memory map").clicked() {
+							self.coverage_view.open = !self.coverage_view.open;
+						}
+					});
+
+					ui.collapsing("Registers", |ui| {
+						let core = self.core().clone();
+						for event in self.register_inspector.show(ui, &core) {
+							self.send_event(event);
+						}
+					});
+
+					ui.collapsing("Trace", |ui| {
+						let core = self.core().clone();
+						match self.trace_controls.show(ui, &core) {
+							Some(TraceAction::Start(filter)) => self.start_trace(filter),
+							Some(TraceAction::Stop) => self.send_event(ch8_core::Event::StopTrace),
+							None => {}
+						}
+					});
+				});
+			});
+	}
+
+	fn start_trace(&mut self, filter: TraceFilter) {
+		let rom_name = rom::rom_file_stem(self.last_rom_path.as_deref());
+
+		let path = match rfd::FileDialog::new()
+			.add_filter("Trace", &[trace::TRACE_EXTENSION])
+			.set_file_name(&format!("{}.{}", rom_name, trace::TRACE_EXTENSION))
+			.save_file()
+		{
+			Some(path) => path,
+			None => return,
+		};
+
+		match TraceRecorder::create(&path, filter) {
+			Ok(recorder) => self.send_event(ch8_core::Event::StartTrace(recorder)),
+			Err(e) => {
+				error!("Error creating trace file {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error creating trace file '{}': {}",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
+	fn export_profile(&mut self, core: &CoreState) {
+		let profile = match &core.profile {
+			Some(profile) => profile,
+			None => return,
+		};
+
+		let rom_name = rom::rom_file_stem(self.last_rom_path.as_deref());
+
+		let path = match rfd::FileDialog::new()
+			.add_filter("CSV", &[profiler::CSV_EXTENSION])
+			.set_file_name(&format!("{}_profile.{}", rom_name, profiler::CSV_EXTENSION))
+			.save_file()
+		{
+			Some(path) => path,
+			None => return,
+		};
+
+		let result = File::create(&path).and_then(|file| profile.write_csv(BufWriter::new(file)));
+		match result {
+			Ok(()) => trace!("Profile exported to {}", path.display()),
+			Err(e) => {
+				error!("Error exporting the profile to {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error exporting the profile to '{}': {}",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
+	///Save the memory map as an image if `png`, otherwise the accessed ranges as CSV
+	fn export_coverage(&mut self, core: &CoreState, png: bool) {
+		let rom_name = rom::rom_file_stem(self.last_rom_path.as_deref());
+
+		let (filter, extension) = if png {
+			("PNG", "png")
+		} else {
+			("CSV", coverage::CSV_EXTENSION)
+		};
+		let path = match rfd::FileDialog::new()
+			.add_filter(filter, &[extension])
+			.set_file_name(&format!("{}_memory_map.{}", rom_name, extension))
+			.save_file()
+		{
+			Some(path) => path,
+			None => return,
+		};
+
+		let result = if png {
+			core.coverage.save_png(&path, COVERAGE_IMAGE_SCALE)
+		} else {
+			File::create(&path).and_then(|file| core.coverage.write_csv(BufWriter::new(file)))
+		};
+		match result {
+			Ok(()) => trace!("Memory map exported to {}", path.display()),
+			Err(e) => {
+				error!(
+					"Error exporting the memory map to {}: {}",
+					path.display(),
+					e
+				);
+
+				self.gui_error = Some(format!(
+					"Error exporting the memory map to '{}': {}",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
 	fn show_keymap_section(&mut self, ui: &mut egui::Ui) {
 		egui::CollapsingHeader::new("Keymap")
 			.default_open(true)
 			.show(ui, |ui| {
 				ui.add_enabled_ui(!self.error_occurred(), |ui| {
+					let overridden = self.keymap_overridden();
+
+					if let Some(overridden) = self.show_profile_toggle(ui, overridden) {
+						let keymap = keymap::to_saved(&self.keymap);
+						let gamepad_map = self.gamepad_map;
+						self.update_rom_profile(|profile| {
+							profile.keymap = overridden.then_some(keymap);
+							profile.gamepad_map = overridden.then_some(gamepad_map);
+						});
+					}
+
+					let keys_down = self.core().keys_down;
+					let keys_polled = self.core().keys_polled;
+					ui.collapsing("Keypad", |ui| {
+						self.keypad_keys = keypad::show(ui, &keys_down, &keys_polled);
+					})
+					.header_response
+					.on_hover_text("Lit keys are held down, outlined keys were checked by the ROM");
+
+					let mut keymap = self.effective_keymap();
+					let mut changed = false;
+
+					ui.horizontal(|ui| {
+						let current = KeymapPreset::find(&keymap);
+
+						egui::ComboBox::from_label("Preset")
+							.selected_text(
+								current
+									.map_or_else(|| "Custom".into(), |preset| preset.to_string()),
+							)
+							.show_ui(ui, |ui| {
+								for preset in KeymapPreset::ALL {
+									let selected = current == Some(preset);
+									if ui.selectable_label(selected, preset.to_string()).clicked() {
+										keymap = preset.keymap();
+										changed = true;
+									}
+								}
+							});
+
+						if ui.button("Import").clicked() {
+							if let Some(imported) = self.import_keymap() {
+								keymap = imported;
+								changed = true;
+							}
+						}
+						if ui.button("Export").clicked() {
+							self.export_keymap(&keymap);
+						}
+					});
+
 					//CHIP-8 Keypad
 					//1 2 3 C
 					//4 5 6 D
 					//7 8 9 E
 					//A 0 B F
+					egui::Grid::new("keymap").show(ui, |ui| {
+						for key in keypad::LAYOUT.into_iter().flatten() {
+							ui.label(RichText::new(format!("{:X} -", key)).monospace());
+
+							for (slot, bind) in keymap[key].iter_mut().enumerate() {
+								changed |= Bind::new(format!("btn_{:X}_{}", key, slot), bind)
+									.ui(ui)
+									.changed();
+							}
 
-					ui.horizontal(|ui| {
-						ui.label(RichText::new("1 -").monospace());
-						Bind::new("btn_1", &mut self.keymap[0x1]).ui(ui).changed();
+							ui.end_row();
+						}
+					});
 
-						ui.label(RichText::new("2 -").monospace());
-						Bind::new("btn_2", &mut self.keymap[0x2]).ui(ui).changed();
+					for (bind, keys) in keymap::conflicts(&keymap) {
+						let keys: Vec<String> =
+							keys.iter().map(|key| format!("{:X}", key)).collect();
 
-						ui.label(RichText::new("3 -").monospace());
-						Bind::new("btn_3", &mut self.keymap[0x3]).ui(ui).changed();
+						ui.colored_label(
+							ui.visuals().warn_fg_color,
+							format!("{} is bound to keys {}", bind, keys.join(", ")),
+						);
+					}
 
-						ui.label(RichText::new("C -").monospace());
-						Bind::new("btn_C", &mut self.keymap[0xC]).ui(ui).changed();
-					});
-					ui.horizontal(|ui| {
-						ui.label(RichText::new("4 -").monospace());
-						Bind::new("btn_4", &mut self.keymap[0x4]).ui(ui).changed();
+					if changed {
+						self.set_keymap(keymap);
+					}
 
-						ui.label(RichText::new("5 -").monospace());
-						Bind::new("btn_5", &mut self.keymap[0x5]).ui(ui).changed();
+					ui.separator();
 
-						ui.label(RichText::new("6 -").monospace());
-						Bind::new("btn_6", &mut self.keymap[0x6]).ui(ui).changed();
+					self.show_gamepad_options(ui);
+				});
+			});
+	}
 
-						ui.label(RichText::new("D -").monospace());
-						Bind::new("btn_D", &mut self.keymap[0xD]).ui(ui).changed();
-					});
-					ui.horizontal(|ui| {
-						ui.label(RichText::new("7 -").monospace());
-						Bind::new("btn_7", &mut self.keymap[0x7]).ui(ui).changed();
+	fn show_hotkeys_section(&mut self, ui: &mut egui::Ui) {
+		egui::CollapsingHeader::new("Hotkeys")
+			.default_open(true)
+			.show(ui, |ui| {
+				egui::Grid::new("hotkeys").show(ui, |ui| {
+					for hotkey in Hotkey::ALL {
+						ui.label(hotkey.to_string());
+						Bind::new(format!("hotkey_{:?}", hotkey), self.hotkeys.get_mut(hotkey))
+							.ui(ui);
+						ui.end_row();
+					}
+				});
 
-						ui.label(RichText::new("8 -").monospace());
-						Bind::new("btn_8", &mut self.keymap[0x8]).ui(ui).changed();
+				let keymap = self.effective_keymap();
+				for (hotkey, bind) in self.hotkeys.conflicts(&keymap) {
+					ui.colored_label(
+						ui.visuals().warn_fg_color,
+						format!("{} ({}) is also bound to another action", hotkey, bind),
+					);
+				}
+				ui.label("Hotkeys take precedence over CHIP-8 keys with the same binding");
 
-						ui.label(RichText::new("9 -").monospace());
-						Bind::new("btn_9", &mut self.keymap[0x9]).ui(ui).changed();
+				if ui.button("Reset to defaults").clicked() {
+					self.hotkeys = HotkeyMap::default();
+				}
+			});
+	}
 
-						ui.label(RichText::new("E -").monospace());
-						Bind::new("btn_E", &mut self.keymap[0xE]).ui(ui).changed();
-					});
-					ui.horizontal(|ui| {
-						ui.label(RichText::new("A -").monospace());
-						Bind::new("btn_A", &mut self.keymap[0xA]).ui(ui).changed();
+	fn import_keymap(&mut self) -> Option<Keymap> {
+		let path = rfd::FileDialog::new()
+			.add_filter("Keymap", &[keymap::KEYMAP_EXTENSION])
+			.pick_file()?;
+
+		match keymap::import(&path) {
+			Ok(keymap) => {
+				trace!("Keymap imported from {}", path.display());
+				Some(keymap)
+			}
+			Err(e) => {
+				error!("Error importing keymap from {}: {}", path.display(), e);
+
+				self.gui_error = Some(format!(
+					"Error importing keymap '{}': {}",
+					path.display(),
+					e
+				));
+				None
+			}
+		}
+	}
 
-						ui.label(RichText::new("0 -").monospace());
-						Bind::new("btn_0", &mut self.keymap[0x0]).ui(ui).changed();
+	fn export_keymap(&mut self, keymap: &Keymap) {
+		let path = match rfd::FileDialog::new()
+			.add_filter("Keymap", &[keymap::KEYMAP_EXTENSION])
+			.set_file_name(&format!("keymap.{}", keymap::KEYMAP_EXTENSION))
+			.save_file()
+		{
+			Some(path) => path,
+			None => return,
+		};
 
-						ui.label(RichText::new("B -").monospace());
-						Bind::new("btn_B", &mut self.keymap[0xB]).ui(ui).changed();
+		match keymap::export(&path, keymap) {
+			Ok(_) => trace!("Keymap exported to {}", path.display()),
+			Err(e) => {
+				error!("Error exporting keymap to {}: {}", path.display(), e);
 
-						ui.label(RichText::new("F -").monospace());
-						Bind::new("btn_F", &mut self.keymap[0xF]).ui(ui).changed();
-					});
-				});
+				self.gui_error = Some(format!(
+					"Error exporting keymap '{}': {}",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
+	fn set_keymap(&mut self, keymap: Keymap) {
+		if self.keymap_overridden() {
+			self.update_rom_profile(|profile| profile.keymap = Some(keymap::to_saved(&keymap)));
+		} else {
+			self.keymap = keymap;
+		}
+	}
+
+	fn show_gamepad_options(&mut self, ui: &mut egui::Ui) {
+		let names = self.gamepads.names();
+		if names.is_empty() {
+			ui.label("No controllers connected");
+		}
+		for name in names {
+			ui.label(format!("Controller: {}", name));
+		}
+
+		let gamepad_map = self.effective_gamepad_map();
+		let mut cleared_key = None;
+
+		for row in keypad::LAYOUT {
+			ui.horizontal(|ui| {
+				for key in row {
+					ui.label(RichText::new(format!("{:X} -", key)).monospace());
+
+					let text = if self.gamepad_binding == Some(key) {
+						"...".into()
+					} else {
+						gamepad_map[key].map_or_else(|| "None".into(), |bind| bind.to_string())
+					};
+
+					let response = ui.button(text).on_hover_text(
+						"Click and press a button or push a stick to bind it, right click to clear",
+					);
+					if response.clicked() {
+						self.gamepad_binding = Some(key);
+					}
+					if response.secondary_clicked() {
+						cleared_key = Some(key);
+					}
+				}
 			});
+		}
+
+		if let Some(key) = cleared_key {
+			let mut gamepad_map = gamepad_map;
+			gamepad_map[key] = None;
+			self.set_gamepad_map(gamepad_map);
+		}
+	}
+
+	fn update_gamepads(&mut self) {
+		self.gamepad_pressed = self.gamepads.update();
+
+		if let (Some(key), Some(&bind)) = (self.gamepad_binding, self.gamepad_pressed.first()) {
+			trace!("Binding {} to key {:X}", bind, key);
+			self.gamepad_binding = None;
+
+			let mut gamepad_map = self.effective_gamepad_map();
+			gamepad_map[key] = Some(bind);
+			self.set_gamepad_map(gamepad_map);
+		}
+	}
+
+	fn update_frame_blender(&mut self) {
+		//Only add frames that were not seen yet, so the filters blend over core frames instead of GUI frames
+		let frames_produced = self.core().frames_produced;
+		if self.blended_frame == Some(frames_produced) {
+			return;
+		}
+		self.blended_frame = Some(frames_produced);
+
+		let frame = self.latest_frame().clone();
+		self.frame_blender.push(&frame);
+	}
+
+	///Size of the game screen inside the area `available`
+	fn game_screen_size(&self, available: egui::Vec2) -> egui::Vec2 {
+		let size = egui::Vec2::new(ch8_core::WIDTH as f32, ch8_core::HEIGHT as f32);
+
+		match self.scale_mode {
+			ScaleMode::Fit => size * self.scale,
+			ScaleMode::Integer => size * self.scale.floor().max(1.0),
+			ScaleMode::Stretch => available,
+		}
 	}
 
-	fn add_game_screen(&mut self, ctx: &Context) {
+	fn add_game_screen(&mut self, ctx: &Context, frame: &mut Frame) {
+		self.update_frame_blender();
+
 		let image = {
-			let size = self.latest_frame().get_size();
-			let buf = self.latest_frame().get_buf();
+			let palette = self.palette();
+			let (size, buf) = self.frame_blender.render(&palette);
+			let (size, buf) = self.upscaler.apply(size, &buf);
 
 			egui_extras::RetainedImage::from_color_image(
 				"game_image",
@@ -478,14 +1651,39 @@ impl Gui {
 		let central_panel = egui::CentralPanel::default()
 			.frame(self.frame_no_margin)
 			.show(ctx, |ui| {
-				image.show_scaled(ui, self.scale);
+				let available = ui.available_rect_before_wrap();
+				ui.painter()
+					.rect_filled(available, 0.0, egui::Color32::BLACK);
+
+				let size = self.game_screen_size(available.size());
+				ui.put(
+					egui::Rect::from_center_size(available.center(), size),
+					egui::Image::new(image.texture_id(ctx), size),
+				);
+
+				if !ctx.input().raw.hovered_files.is_empty() {
+					ui.painter()
+						.rect_filled(available, 0.0, egui::Color32::from_black_alpha(180));
+					ui.painter().text(
+						available.center(),
+						egui::Align2::CENTER_CENTER,
+						"Drop file to load",
+						egui::FontId::proportional(32.0),
+						egui::Color32::WHITE,
+					);
+				}
 			});
 
-		if !self.error_occurred() {
-			central_panel.response.context_menu(|ui| {
+		central_panel.response.context_menu(|ui| {
+			if !self.error_occurred() {
 				self.show_running_and_step_frame(ui);
-			});
-		}
+				self.show_screenshot_button(ui);
+
+				ui.separator();
+			}
+
+			self.show_window_options(frame, ui);
+		});
 	}
 
 	fn show_running_and_step_frame(&mut self, ui: &mut egui::Ui) {
@@ -507,12 +1705,127 @@ impl Gui {
 		//TODO Add step opcode button
 	}
 
+	fn show_screenshot_button(&mut self, ui: &mut egui::Ui) {
+		if ui.button(self.hotkeys.label(Hotkey::Screenshot)).clicked() {
+			self.take_screenshot();
+			ui.close_menu();
+		}
+	}
+
+	fn take_screenshot(&mut self) {
+		let path = ch8_core::screenshot::path(
+			self.screenshot_directory.as_deref(),
+			self.last_rom_path.as_deref(),
+		);
+		let image = self.latest_frame().clone();
+		let palette = self.palette();
+
+		match ch8_core::screenshot::save_png(
+			&path,
+			&image,
+			&palette,
+			self.upscaler,
+			self.screenshot_scale,
+		) {
+			Ok(()) => trace!("Screenshot saved to {}", path.display()),
+			Err(e) => {
+				error!(
+					"Error while saving screenshot to {} ({})",
+					path.display(),
+					e
+				);
+
+				self.gui_error = Some(format!(
+					"Error while saving screenshot to '{}': '{}'",
+					path.display(),
+					e
+				));
+			}
+		}
+	}
+
+	fn handle_hotkeys(&mut self, ctx: &Context, frame: &mut Frame) {
+		//Typing into a text field must not trigger hotkeys.
+		//Held hotkeys count as released, so fast forward and rewinding stop.
+		let typing = ctx.wants_keyboard_input();
+		let hotkeys = self.hotkeys;
+		let pressed = |hotkey| {
+			!typing
+				&& hotkeys
+					.get(hotkey)
+					.is_some_and(|bind| bind.pressed(ctx.input()))
+		};
+		let down = |hotkey| {
+			!typing
+				&& hotkeys
+					.get(hotkey)
+					.is_some_and(|bind| bind.down(ctx.input()))
+		};
+
+		if pressed(Hotkey::Fullscreen) {
+			self.set_fullscreen(frame, !self.fullscreen);
+		}
+		if pressed(Hotkey::SideMenu) {
+			self.side_menu_visible = !self.side_menu_visible;
+		}
+		if pressed(Hotkey::ScaleMode) {
+			self.scale_mode = self.scale_mode.next();
+			trace!("Scale mode: {}", self.scale_mode);
+		}
+
+		if self.error_occurred() {
+			return;
+		}
+
+		if pressed(Hotkey::Screenshot) {
+			self.take_screenshot();
+		}
+		if pressed(Hotkey::Reset) {
+			self.reset_core_keep_rom(ctx);
+		}
+
+		let core = self.core().clone();
+		if core.rom_name.is_none() {
+			return;
+		}
+
+		if pressed(Hotkey::Pause) {
+			self.send_event(ch8_core::Event::ChangeRunning(!core.running));
+		}
+		if pressed(Hotkey::StepFrame) && !core.running {
+			self.send_event(ch8_core::Event::StepFrame);
+		}
+		if pressed(Hotkey::SaveState) {
+			self.send_event(ch8_core::Event::SaveState);
+		}
+		if pressed(Hotkey::LoadState) {
+			self.send_event(ch8_core::Event::LoadState);
+		}
+
+		let fast_forward = down(Hotkey::FastForward);
+		if fast_forward != core.fast_forward {
+			self.send_event(ch8_core::Event::ChangeFastForward(fast_forward));
+		}
+		let rewinding = down(Hotkey::Rewind);
+		if rewinding != core.rewinding {
+			self.send_event(ch8_core::Event::ChangeRewinding(rewinding));
+		}
+	}
+
 	fn check_core_error(&mut self, ctx: &Context) {
 		let core = self.core().clone();
 
 		if let Some(error) = &core.error {
-			if self.show_error_window(ctx, &error.to_string()) {
+			let mut save_report = false;
+			let details = |ui: &mut egui::Ui| {
+				debugger::show_error_details(ui, error, &core.memory);
+				save_report = ui.button("Save report…").clicked();
+			};
+
+			if self.show_error_window(ctx, &error.to_string(), details) {
 				self.reset_core(ctx);
+			} else if save_report {
+				self.save_crash_report(&core);
 			}
 		}
 	}
@@ -520,8 +1833,7 @@ impl Gui {
 	fn reset_core(&mut self, ctx: &Context) {
 		trace!("Resetting core");
 
-		//Keep these settings between resets
-		let opcodes_per_frame = self.core().opcodes_per_frame;
+		self.crash_report_error = None;
 
 		self.send_event(ch8_core::Event::Exit);
 
@@ -534,21 +1846,34 @@ impl Gui {
 		}));
 		self.emu_core = emu_core;
 
-		self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(opcodes_per_frame));
+		self.send_event(ch8_core::Event::ChangeOpcodesPerFrame(
+			self.opcodes_per_frame,
+		));
+		self.send_event(ch8_core::Event::ChangeQuirks(self.quirks));
 	}
 
 	fn reset_core_keep_rom(&mut self, ctx: &Context) {
 		self.reset_core(ctx);
 
 		if let Some(path) = self.last_rom_path.clone() {
-			self.send_event(ch8_core::Event::LoadRom(path));
-			self.send_event(ch8_core::Event::ChangeRunning(true));
+			self.start_rom(path);
+		}
+	}
+
+	fn check_crash_report_error(&mut self, ctx: &Context) {
+		if let Some(error) = &self.crash_report_error {
+			let memory = self.emu_core.get_state().memory;
+			let details = |ui: &mut egui::Ui| debugger::show_error_details(ui, error, &memory);
+
+			if self.show_error_window(ctx, &format!("Crash report: {}", error), details) {
+				self.crash_report_error = None;
+			}
 		}
 	}
 
 	fn check_gui_error(&mut self, ctx: &Context) {
 		if let Some(error) = &self.gui_error {
-			if self.show_error_window(ctx, error) {
+			if self.show_error_window(ctx, error, |_| {}) {
 				self.gui_error = None;
 			}
 		}
@@ -558,12 +1883,18 @@ impl Gui {
 		self.core().error.is_some() || self.gui_error.is_some()
 	}
 
-	fn show_error_window(&self, ctx: &Context, error: &str) -> bool {
+	fn show_error_window(
+		&self,
+		ctx: &Context,
+		error: &str,
+		add_details: impl FnOnce(&mut egui::Ui),
+	) -> bool {
 		let mut clicked = false;
 		egui::Window::new("Error")
 			.frame(self.transparent_frame)
 			.show(ctx, |ui| {
 				ui.colored_label(ui.visuals().error_fg_color, error);
+				add_details(ui);
 
 				clicked = ui.button("Ok").clicked();
 			});
@@ -592,21 +1923,162 @@ impl Gui {
 		self.emu_core.get_state()
 	}
 
+	///Settings profile of the loaded ROM
+	fn rom_profile(&mut self) -> Option<RomProfile> {
+		let hash = self.core().rom_hash.clone()?;
+		self.rom_profiles.get(&hash).cloned()
+	}
+
+	///Change the settings profile of the loaded ROM, empty profiles are removed
+	fn update_rom_profile(&mut self, update: impl FnOnce(&mut RomProfile)) {
+		let hash = match self.core().rom_hash.clone() {
+			Some(hash) => hash,
+			None => return,
+		};
+
+		let profile = self.rom_profiles.entry(hash.clone()).or_default();
+		update(profile);
+
+		if profile.is_empty() {
+			self.rom_profiles.remove(&hash);
+		}
+	}
+
+	///Radio buttons to switch a side menu section between the global defaults and the settings of the loaded ROM.
+	///Returns the new state if it was changed.
+	fn show_profile_toggle(&mut self, ui: &mut egui::Ui, overridden: bool) -> Option<bool> {
+		self.core().rom_hash.as_ref()?;
+
+		let mut new_overridden = overridden;
+		ui.horizontal(|ui| {
+			ui.radio_value(&mut new_overridden, false, "Global default");
+			ui.radio_value(&mut new_overridden, true, "Override for this ROM");
+		});
+
+		(new_overridden != overridden).then_some(new_overridden)
+	}
+
+	fn rom_metadata(&mut self) -> Option<RomMetadata> {
+		self.core().rom_metadata.clone()
+	}
+
+	///Opcodes per frame from the ROM profile, the ROM database or the global default
+	fn effective_opcodes_per_frame(&mut self) -> u32 {
+		self.rom_profile()
+			.and_then(|profile| profile.opcodes_per_frame)
+			.or_else(|| {
+				self.rom_metadata()
+					.and_then(|metadata| metadata.opcodes_per_frame)
+			})
+			.unwrap_or(self.opcodes_per_frame)
+	}
+
+	///Quirks from the ROM profile, the ROM database or the global default
+	fn effective_quirks(&mut self) -> Quirks {
+		self.rom_profile()
+			.and_then(|profile| profile.quirks)
+			.or_else(|| self.rom_metadata().and_then(|metadata| metadata.quirks))
+			.unwrap_or(self.quirks)
+	}
+
+	///Keymap from the ROM profile or the global keymap
+	fn effective_keymap(&mut self) -> Keymap {
+		self.rom_profile()
+			.and_then(|profile| profile.keymap)
+			.map(|keymap| keymap::from_saved(&keymap))
+			.unwrap_or(self.keymap)
+	}
+
+	fn keymap_overridden(&mut self) -> bool {
+		self.rom_profile()
+			.is_some_and(|profile| profile.keymap.is_some() || profile.gamepad_map.is_some())
+	}
+
+	///Gamepad map from the ROM profile or the global gamepad map
+	fn effective_gamepad_map(&mut self) -> GamepadMap {
+		gamepad::effective_map(self.gamepad_map, self.rom_profile().as_ref())
+	}
+
+	fn set_gamepad_map(&mut self, gamepad_map: GamepadMap) {
+		if self.keymap_overridden() {
+			self.update_rom_profile(|profile| profile.gamepad_map = Some(gamepad_map));
+		} else {
+			self.gamepad_map = gamepad_map;
+		}
+	}
+
+	///Palette from the ROM profile, the ROM database or the default palette
+	fn palette(&mut self) -> Palette {
+		self.rom_profile()
+			.and_then(|profile| profile.palette)
+			.or_else(|| self.database_palette())
+			.unwrap_or(self.palette)
+	}
+
+	fn database_palette(&mut self) -> Option<Palette> {
+		self.core()
+			.rom_metadata
+			.as_ref()
+			.and_then(|metadata| metadata.palette)
+	}
+
 	fn send_keys_to_core(&mut self, ctx: &Context) {
-		let keys: [bool; 16] = (0..16)
-			.map(|i| self.keymap[i].down(ctx.input()))
-			.collect::<Vec<bool>>()
-			.try_into()
-			.expect("Shouldn't fail because the mapped range contains 16 elements");
+		let keymap = self.effective_keymap();
+		let gamepad_map = self.effective_gamepad_map();
+		let gamepad_keys = self.gamepads.keys_down(&gamepad_map);
+		//The keypad sets its keys again every frame it is shown, hidden keypads cannot hold keys down
+		let keypad_keys = std::mem::take(&mut self.keypad_keys);
+		let hotkeys = self.hotkeys;
+		//Keys typed into a text field are not meant for the ROM, gamepads and the keypad still are
+		let typing = ctx.wants_keyboard_input();
+		let time = Instant::now();
+
+		for key in 0..16 {
+			let binds = || {
+				keymap[key]
+					.iter()
+					.flatten()
+					.filter(|bind| !typing && !hotkeys.contains(bind))
+			};
+
+			let down =
+				binds().any(|bind| bind.down(ctx.input())) || gamepad_keys[key] || keypad_keys[key];
+			//Keys that were pressed and released again since the last GUI frame
+			let tapped = binds().any(|bind| bind.pressed(ctx.input()))
+				|| gamepad_map[key].is_some_and(|bind| self.gamepad_pressed.contains(&bind));
+
+			if down && !self.keys_sent[key] {
+				self.send_event(ch8_core::Event::KeyDown(key as u8, time));
+			} else if !down && self.keys_sent[key] {
+				self.send_event(ch8_core::Event::KeyUp(key as u8, time));
+			} else if !down && tapped {
+				self.send_event(ch8_core::Event::KeyDown(key as u8, time));
+				self.send_event(ch8_core::Event::KeyUp(key as u8, time));
+			}
 
-		//TODO Don't send event if keys down haven't changed
-		self.send_event(ch8_core::Event::KeysDown(keys));
+			self.keys_sent[key] = down;
+		}
 	}
 }
 
 impl eframe::App for Gui {
+	fn save(&mut self, storage: &mut dyn eframe::Storage) {
+		trace!("Saving settings");
+
+		let settings = self.settings();
+		eframe::set_value(storage, eframe::APP_KEY, &settings);
+
+		if self.resume_last_rom {
+			self.auto_save();
+		}
+	}
+
 	fn update(&mut self, ctx: &Context, frame: &mut Frame) {
-		self.add_side_menu(ctx, frame);
+		if self.side_menu_visible {
+			self.add_side_menu(ctx, frame);
+		} else {
+			self.side_menu_width = 0.0;
+		}
 
 		//Setup has to be called after `add_side_menu()`
 		//because this sets `self.side_menu_width` to the correct value
@@ -616,13 +2088,54 @@ impl eframe::App for Gui {
 			self.setup(ctx, frame);
 		}
 
-		self.add_game_screen(ctx);
+		self.add_game_screen(ctx, frame);
+
+		let palette = self.palette;
+		if let Some(path) = self.library.show(ctx, palette) {
+			self.open_file(ctx, path);
+		}
 
+		let core = self.core().clone();
+		if let Some((address, bytes)) = self.memory_viewer.show(ctx, &core) {
+			self.send_event(ch8_core::Event::WriteMemory { address, bytes });
+		}
+		for event in self.disassembly.show(ctx, &core) {
+			self.send_event(event);
+		}
+		let palette = self.palette();
+		if let Some((address, value)) = self.sprite_viewer.show(ctx, &core, palette) {
+			self.send_event(ch8_core::Event::WriteMemory {
+				address,
+				bytes: vec![value],
+			});
+		}
+		match self.profiler_view.show(ctx, &core) {
+			Some(ProfilerAction::Enable(enabled)) => {
+				self.send_event(ch8_core::Event::ChangeProfiling(enabled))
+			}
+			Some(ProfilerAction::Reset) => self.send_event(ch8_core::Event::ResetProfile),
+			Some(ProfilerAction::Export) => self.export_profile(&core),
+			None => {}
+		}
+		match self.coverage_view.show(ctx, &core) {
+			Some(CoverageAction::Reset) => self.send_event(ch8_core::Event::ResetCoverage),
+			Some(CoverageAction::ExportCsv) => self.export_coverage(&core, false),
+			Some(CoverageAction::ExportPng) => self.export_coverage(&core, true),
+			None => {}
+		}
+
+		self.update_gamepads();
 		self.send_keys_to_core(ctx);
 
+		self.handle_dropped_files(ctx);
+
+		self.handle_hotkeys(ctx, frame);
+
 		self.check_core_error(ctx);
+		self.check_crash_report_error(ctx);
 		self.check_gui_error(ctx);
 
 		self.update_scale(ctx);
+		self.snap_to_scale(ctx, frame);
 	}
 }
@@ -0,0 +1,155 @@
+use crate::keymap;
+use crate::keymap::{Bind, Keymap};
+use crate::settings::{SavedBind, SavedKeyBind};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Formatter;
+
+///Emulator actions that can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Hotkey {
+	Pause,
+	StepFrame,
+	Reset,
+	SaveState,
+	LoadState,
+	///Held down
+	FastForward,
+	///Held down
+	Rewind,
+	Screenshot,
+	Fullscreen,
+	SideMenu,
+	ScaleMode,
+}
+
+impl Hotkey {
+	pub const ALL: [Hotkey; 11] = [
+		Hotkey::Pause,
+		Hotkey::StepFrame,
+		Hotkey::Reset,
+		Hotkey::SaveState,
+		Hotkey::LoadState,
+		Hotkey::FastForward,
+		Hotkey::Rewind,
+		Hotkey::Screenshot,
+		Hotkey::Fullscreen,
+		Hotkey::SideMenu,
+		Hotkey::ScaleMode,
+	];
+
+	fn default_bind(&self) -> Option<Bind> {
+		use egui::Key::*;
+
+		let key = match self {
+			Hotkey::Pause => Space,
+			Hotkey::StepFrame => N,
+			Hotkey::Reset => F3,
+			Hotkey::SaveState => F5,
+			Hotkey::LoadState => F7,
+			Hotkey::FastForward => ArrowRight,
+			Hotkey::Rewind => ArrowLeft,
+			Hotkey::Screenshot => F12,
+			Hotkey::Fullscreen => F11,
+			Hotkey::SideMenu => F10,
+			Hotkey::ScaleMode => F9,
+		};
+
+		Some((egui_bind::KeyOrPointer::Key(key), egui::Modifiers::NONE))
+	}
+}
+
+impl fmt::Display for Hotkey {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			Hotkey::Pause => write!(f, "Pause"),
+			Hotkey::StepFrame => write!(f, "Step frame"),
+			Hotkey::Reset => write!(f, "Reset ROM"),
+			Hotkey::SaveState => write!(f, "Save state"),
+			Hotkey::LoadState => write!(f, "Load state"),
+			Hotkey::FastForward => write!(f, "Fast-forward"),
+			Hotkey::Rewind => write!(f, "Rewind"),
+			Hotkey::Screenshot => write!(f, "Screenshot"),
+			Hotkey::Fullscreen => write!(f, "Fullscreen"),
+			Hotkey::SideMenu => write!(f, "Side menu"),
+			Hotkey::ScaleMode => write!(f, "Scale mode"),
+		}
+	}
+}
+
+///Bindings of all hotkeys, indexed by `Hotkey as usize`
+#[derive(Clone, Copy)]
+pub struct HotkeyMap([Option<Bind>; Hotkey::ALL.len()]);
+
+impl HotkeyMap {
+	pub fn get(&self, hotkey: Hotkey) -> Option<Bind> {
+		self.0[hotkey as usize]
+	}
+
+	pub fn get_mut(&mut self, hotkey: Hotkey) -> &mut Option<Bind> {
+		&mut self.0[hotkey as usize]
+	}
+
+	///Label of `hotkey` with its binding, e.g. "Screenshot (F12)"
+	pub fn label(&self, hotkey: Hotkey) -> String {
+		match self.get(hotkey) {
+			Some((bind, _)) => format!("{} ({})", hotkey, SavedBind::from(bind)),
+			None => hotkey.to_string(),
+		}
+	}
+
+	///Whether `bind` is used by a hotkey. CHIP-8 keys with the same binding are ignored.
+	pub fn contains(&self, bind: &Bind) -> bool {
+		let bind = to_saved(bind);
+		self.0.iter().flatten().any(|other| to_saved(other) == bind)
+	}
+
+	///Hotkeys that share their binding with another hotkey or a CHIP-8 key
+	pub fn conflicts(&self, keymap: &Keymap) -> Vec<(Hotkey, SavedBind)> {
+		let keymap = keymap::to_saved(keymap);
+
+		Hotkey::ALL
+			.into_iter()
+			.filter_map(|hotkey| Some((hotkey, to_saved(&self.get(hotkey)?))))
+			.filter(|(hotkey, bind)| {
+				let used_by_hotkey = Hotkey::ALL.into_iter().any(|other| {
+					other != *hotkey && self.get(other).map(|other| to_saved(&other)) == Some(*bind)
+				});
+				let used_by_key = keymap.iter().flatten().flatten().any(|key| key == bind);
+
+				used_by_hotkey || used_by_key
+			})
+			.map(|(hotkey, (bind, _))| (hotkey, bind))
+			.collect()
+	}
+
+	pub fn to_saved(self) -> HashMap<Hotkey, Option<SavedKeyBind>> {
+		Hotkey::ALL
+			.into_iter()
+			.map(|hotkey| (hotkey, self.get(hotkey).map(|bind| to_saved(&bind))))
+			.collect()
+	}
+
+	///Hotkeys that are missing in `saved` keep their default binding
+	pub fn from_saved(saved: &HashMap<Hotkey, Option<SavedKeyBind>>) -> Self {
+		let mut map = Self::default();
+
+		for (hotkey, bind) in saved {
+			*map.get_mut(*hotkey) = bind.map(|(bind, modifiers)| (bind.into(), modifiers));
+		}
+
+		map
+	}
+}
+
+impl Default for HotkeyMap {
+	fn default() -> Self {
+		Self(Hotkey::ALL.map(|hotkey| hotkey.default_bind()))
+	}
+}
+
+fn to_saved(bind: &Bind) -> SavedKeyBind {
+	(bind.0.into(), bind.1)
+}
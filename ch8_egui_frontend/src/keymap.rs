@@ -0,0 +1,169 @@
+use crate::keypad;
+use crate::settings::{SavedBind, SavedKeymap};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fmt::Formatter;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+///Number of bindings each CHIP-8 key can have
+pub const BINDS_PER_KEY: usize = 2;
+
+pub type Bind = (egui_bind::KeyOrPointer, egui::Modifiers);
+pub type Keymap = [[Option<Bind>; BINDS_PER_KEY]; 16];
+
+pub const KEYMAP_EXTENSION: &str = "json";
+
+///Keymaps for common keyboard layouts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeymapPreset {
+	Qwerty,
+	Qwertz,
+	Azerty,
+	Dvorak,
+	///Digits on the number keys or numpad, A-F on the letter keys
+	Numpad,
+}
+
+impl KeymapPreset {
+	pub const ALL: [KeymapPreset; 5] = [
+		KeymapPreset::Qwerty,
+		KeymapPreset::Qwertz,
+		KeymapPreset::Azerty,
+		KeymapPreset::Dvorak,
+		KeymapPreset::Numpad,
+	];
+
+	pub fn keymap(&self) -> Keymap {
+		use egui::Key::*;
+
+		match self {
+			KeymapPreset::Qwerty => from_layout([
+				[Num1, Num2, Num3, Num4],
+				[Q, W, E, R],
+				[A, S, D, F],
+				[Z, X, C, V],
+			]),
+			KeymapPreset::Qwertz => from_layout([
+				[Num1, Num2, Num3, Num4],
+				[Q, W, E, R],
+				[A, S, D, F],
+				[Y, X, C, V],
+			]),
+			KeymapPreset::Azerty => from_layout([
+				[Num1, Num2, Num3, Num4],
+				[A, Z, E, R],
+				[Q, S, D, F],
+				[W, X, C, V],
+			]),
+			//egui has no keys for the punctuation in the top left of Dvorak,
+			//so this uses the first block to the right that only contains letters and digits
+			KeymapPreset::Dvorak => from_layout([
+				[Num4, Num5, Num6, Num7],
+				[P, Y, F, G],
+				[U, I, D, H],
+				[K, X, B, M],
+			]),
+			KeymapPreset::Numpad => {
+				let keys = [
+					Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, A, B, C, D, E, F,
+				];
+				keys.map(|key| [Some(bind(key)), None])
+			}
+		}
+	}
+
+	///Preset that `keymap` was created from
+	pub fn find(keymap: &Keymap) -> Option<KeymapPreset> {
+		let keymap = to_saved(keymap);
+
+		KeymapPreset::ALL
+			.into_iter()
+			.find(|preset| to_saved(&preset.keymap()) == keymap)
+	}
+}
+
+impl fmt::Display for KeymapPreset {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			KeymapPreset::Qwerty => write!(f, "QWERTY"),
+			KeymapPreset::Qwertz => write!(f, "QWERTZ"),
+			KeymapPreset::Azerty => write!(f, "AZERTY"),
+			KeymapPreset::Dvorak => write!(f, "Dvorak"),
+			KeymapPreset::Numpad => write!(f, "Numpad"),
+		}
+	}
+}
+
+///Keymap of the first launch, other layouts can pick a preset in the keymap options
+pub fn default_keymap() -> Keymap {
+	KeymapPreset::Qwerty.keymap()
+}
+
+fn bind(key: egui::Key) -> Bind {
+	(egui_bind::KeyOrPointer::Key(key), egui::Modifiers::NONE)
+}
+
+///Keymap from a 4x4 block of keys that is laid out like the COSMAC VIP keypad
+fn from_layout(rows: [[egui::Key; 4]; 4]) -> Keymap {
+	let mut keymap: Keymap = [[None; BINDS_PER_KEY]; 16];
+
+	for (row, keys) in keypad::LAYOUT.iter().enumerate() {
+		for (col, key) in keys.iter().enumerate() {
+			keymap[*key][0] = Some(bind(rows[row][col]));
+		}
+	}
+
+	keymap
+}
+
+pub fn to_saved(keymap: &Keymap) -> SavedKeymap {
+	keymap.map(|binds| binds.map(|bind| bind.map(|(bind, modifiers)| (bind.into(), modifiers))))
+}
+
+pub fn from_saved(keymap: &SavedKeymap) -> Keymap {
+	keymap.map(|binds| binds.map(|bind| bind.map(|(bind, modifiers)| (bind.into(), modifiers))))
+}
+
+///Bindings that are used by more than one CHIP-8 key, together with these keys
+pub fn conflicts(keymap: &Keymap) -> Vec<(SavedBind, Vec<usize>)> {
+	let keymap = to_saved(keymap);
+	let mut conflicts: Vec<((SavedBind, egui::Modifiers), Vec<usize>)> = vec![];
+
+	for (key, binds) in keymap.iter().enumerate() {
+		for bind in binds.iter().flatten() {
+			match conflicts.iter_mut().find(|(other, _)| other == bind) {
+				Some((_, keys)) => {
+					if !keys.contains(&key) {
+						keys.push(key);
+					}
+				}
+				None => conflicts.push((*bind, vec![key])),
+			}
+		}
+	}
+
+	conflicts
+		.into_iter()
+		.filter(|(_, keys)| keys.len() > 1)
+		.map(|((bind, _), keys)| (bind, keys))
+		.collect()
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeymapFile {
+	keymap: SavedKeymap,
+}
+
+pub fn export(path: &Path, keymap: &Keymap) -> io::Result<()> {
+	let file = KeymapFile {
+		keymap: to_saved(keymap),
+	};
+
+	fs::write(path, serde_json::to_string_pretty(&file)?)
+}
+
+pub fn import(path: &Path) -> io::Result<Keymap> {
+	let file: KeymapFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+	Ok(from_saved(&file.keymap))
+}
@@ -0,0 +1,53 @@
+use eframe::egui;
+
+///CHIP-8 keys in the layout of the COSMAC VIP keypad
+pub const LAYOUT: [[usize; 4]; 4] = [
+	[0x1, 0x2, 0x3, 0xC],
+	[0x4, 0x5, 0x6, 0xD],
+	[0x7, 0x8, 0x9, 0xE],
+	[0xA, 0x0, 0xB, 0xF],
+];
+
+const KEY_SIZE: f32 = 32.0;
+
+///Show a clickable keypad. Keys in `keys_down` are lit, keys in `keys_polled` are outlined.
+///Returns the keys that are held down with the mouse or a touch, or were clicked this frame.
+pub fn show(ui: &mut egui::Ui, keys_down: &[bool; 16], keys_polled: &[bool; 16]) -> [bool; 16] {
+	let mut pressed = [false; 16];
+
+	egui::Grid::new("keypad")
+		.spacing(egui::Vec2::splat(4.0))
+		.show(ui, |ui| {
+			for row in LAYOUT {
+				for key in row {
+					let (rect, response) = ui.allocate_exact_size(
+						egui::Vec2::splat(KEY_SIZE),
+						egui::Sense::click_and_drag(),
+					);
+					//Clicks that are shorter than a frame still count as pressed for this frame
+					pressed[key] = response.is_pointer_button_down_on() || response.clicked();
+
+					let lit = keys_down[key] || pressed[key];
+					let visuals = ui.style().interact_selectable(&response, lit);
+					let stroke = if keys_polled[key] {
+						ui.visuals().selection.stroke
+					} else {
+						visuals.bg_stroke
+					};
+
+					let painter = ui.painter();
+					painter.rect(rect, visuals.rounding, visuals.bg_fill, stroke);
+					painter.text(
+						rect.center(),
+						egui::Align2::CENTER_CENTER,
+						format!("{:X}", key),
+						egui::TextStyle::Monospace.resolve(ui.style()),
+						visuals.text_color(),
+					);
+				}
+				ui.end_row();
+			}
+		});
+
+	pressed
+}
@@ -0,0 +1,333 @@
+use ch8_core::framebuffer::Framebuffer;
+use ch8_core::palette::Palette;
+use ch8_core::rom::FileKind;
+use eframe::egui;
+use log::{trace, warn};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::{fs, panic, thread};
+
+///Frames a ROM runs headless before its screen is taken as thumbnail
+const THUMBNAIL_FRAMES: u32 = 120;
+const THUMBNAIL_SCALE: f32 = 2.0;
+const MAX_RECENT_ROMS: usize = 10;
+
+///Screen of a ROM after it ran for [`THUMBNAIL_FRAMES`] frames
+enum Thumbnail {
+	///Still being created on the thumbnail thread
+	Pending,
+	Ready(Framebuffer),
+	///The ROM stopped with an error or the core panicked
+	Failed,
+}
+
+struct RomEntry {
+	path: PathBuf,
+	name: String,
+	size: u64,
+	kind: FileKind,
+	thumbnail: Thumbnail,
+	texture: Option<(Palette, egui::TextureHandle)>,
+}
+
+impl RomEntry {
+	fn new(path: PathBuf, size: u64, kind: FileKind) -> Self {
+		let name = path
+			.file_name()
+			.map(|name| name.to_string_lossy().to_string())
+			.unwrap_or_else(|| "<Filename cannot be displayed>".into());
+
+		Self {
+			kind,
+			path,
+			name,
+			size,
+			thumbnail: Thumbnail::Pending,
+			texture: None,
+		}
+	}
+
+	fn texture(&mut self, ctx: &egui::Context, palette: Palette) -> Option<&egui::TextureHandle> {
+		let thumbnail = match &self.thumbnail {
+			Thumbnail::Ready(thumbnail) => thumbnail,
+			Thumbnail::Pending | Thumbnail::Failed => return None,
+		};
+
+		//Recreate the texture if the palette changed since it was created
+		if !matches!(&self.texture, Some((texture_palette, _)) if *texture_palette == palette) {
+			let (size, buf) = thumbnail.render(&palette, 1);
+			let texture = ctx.load_texture(
+				format!("thumbnail_{}", self.path.display()),
+				egui::ColorImage::from_rgba_unmultiplied(size, &buf),
+				egui::TextureOptions::NEAREST,
+			);
+
+			self.texture = Some((palette, texture));
+		}
+
+		self.texture.as_ref().map(|(_, texture)| texture)
+	}
+}
+
+///Run the ROMs at `paths` one after another on a new thread and send back their screens,
+///`None` if a ROM failed. The thread stops early when the receiver is dropped, e.g. by a rescan.
+fn create_thumbnails(
+	ctx: egui::Context,
+	paths: Vec<PathBuf>,
+) -> Receiver<(PathBuf, Option<Framebuffer>)> {
+	let (sender, receiver) = mpsc::channel();
+
+	thread::spawn(move || {
+		for path in paths {
+			let thumbnail = create_thumbnail(&path);
+			if sender.send((path, thumbnail)).is_err() {
+				break;
+			}
+			ctx.request_repaint();
+		}
+	});
+
+	receiver
+}
+
+fn create_thumbnail(path: &Path) -> Option<Framebuffer> {
+	//A ROM that makes the core panic must not take the library down with it
+	let result = panic::catch_unwind(|| {
+		ch8_core::run_headless(path.to_path_buf(), THUMBNAIL_FRAMES, None, None, None)
+	});
+
+	match result {
+		Ok(Ok(state)) => Some(state.image),
+		Ok(Err(e)) => {
+			warn!("Cannot create thumbnail for {} ({})", path.display(), e);
+			None
+		}
+		Err(_) => {
+			warn!(
+				"Cannot create thumbnail for {}, the core panicked",
+				path.display()
+			);
+			None
+		}
+	}
+}
+
+///Browser for the ROMs inside the configured directories
+#[derive(Default)]
+pub struct Library {
+	pub open: bool,
+	pub directories: Vec<PathBuf>,
+	///Most recently loaded ROM first
+	pub recent: Vec<PathBuf>,
+	entries: Vec<RomEntry>,
+	///Thumbnails of `entries` finished by the thumbnail thread
+	thumbnails: Option<Receiver<(PathBuf, Option<Framebuffer>)>>,
+	search: String,
+	scanned: bool,
+}
+
+impl Library {
+	pub fn new(directories: Vec<PathBuf>, recent: Vec<PathBuf>) -> Self {
+		Self {
+			directories,
+			recent,
+			..Default::default()
+		}
+	}
+
+	pub fn add_recent(&mut self, path: PathBuf) {
+		self.recent.retain(|recent| *recent != path);
+		self.recent.insert(0, path);
+		self.recent.truncate(MAX_RECENT_ROMS);
+	}
+
+	pub fn scan(&mut self, ctx: &egui::Context) {
+		self.entries.clear();
+
+		for directory in self.directories.clone() {
+			trace!("Scanning ROM directory: {}", directory.display());
+			self.scan_directory(&directory);
+		}
+
+		self.entries.sort_by_key(|entry| entry.name.to_lowercase());
+		self.scanned = true;
+
+		let paths = self
+			.entries
+			.iter()
+			.map(|entry| entry.path.clone())
+			.collect();
+		self.thumbnails = Some(create_thumbnails(ctx.clone(), paths));
+	}
+
+	fn receive_thumbnails(&mut self) {
+		let thumbnails = match &self.thumbnails {
+			Some(thumbnails) => thumbnails,
+			None => return,
+		};
+
+		for (path, thumbnail) in thumbnails.try_iter() {
+			if let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == path) {
+				entry.thumbnail = match thumbnail {
+					Some(thumbnail) => Thumbnail::Ready(thumbnail),
+					None => Thumbnail::Failed,
+				};
+			}
+		}
+	}
+
+	fn scan_directory(&mut self, directory: &Path) {
+		let dir_entries = match fs::read_dir(directory) {
+			Ok(dir_entries) => dir_entries,
+			Err(e) => {
+				warn!("Cannot read ROM directory {} ({})", directory.display(), e);
+				return;
+			}
+		};
+
+		for dir_entry in dir_entries.flatten() {
+			let path = dir_entry.path();
+			let metadata = match dir_entry.metadata() {
+				Ok(metadata) => metadata,
+				Err(_) => continue,
+			};
+
+			if metadata.is_dir() {
+				self.scan_directory(&path);
+				continue;
+			}
+
+			if let Some(kind) = FileKind::detect(&path).filter(FileKind::is_rom) {
+				self.entries.push(RomEntry::new(path, metadata.len(), kind));
+			}
+		}
+	}
+
+	///Shows the library window if it is open, returns the ROM that was double-clicked
+	pub fn show(&mut self, ctx: &egui::Context, palette: Palette) -> Option<PathBuf> {
+		if !self.open {
+			return None;
+		}
+
+		if !self.scanned {
+			self.scan(ctx);
+		}
+		self.receive_thumbnails();
+
+		let mut open = self.open;
+		let mut picked = None;
+
+		egui::Window::new("ROM library")
+			.open(&mut open)
+			.default_size([500.0, 600.0])
+			.show(ctx, |ui| {
+				self.show_directories(ctx, ui);
+
+				ui.separator();
+
+				ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search"));
+
+				ui.separator();
+
+				picked = self.show_entries(ctx, ui, palette);
+			});
+
+		self.open = open;
+
+		picked
+	}
+
+	fn show_directories(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+		let mut removed = None;
+
+		for (i, directory) in self.directories.iter().enumerate() {
+			ui.horizontal(|ui| {
+				if ui.button("Remove").clicked() {
+					removed = Some(i);
+				}
+				ui.label(directory.display().to_string());
+			});
+		}
+
+		let mut changed = false;
+
+		if let Some(i) = removed {
+			self.directories.remove(i);
+			changed = true;
+		}
+
+		ui.horizontal(|ui| {
+			if ui.button("Add folder").clicked() {
+				if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+					trace!("ROM directory picked: {}", directory.display());
+					self.directories.push(directory);
+					changed = true;
+				}
+			}
+
+			if ui.button("Rescan").clicked() {
+				changed = true;
+			}
+		});
+
+		if changed {
+			self.scan(ctx);
+		}
+	}
+
+	fn show_entries(
+		&mut self,
+		ctx: &egui::Context,
+		ui: &mut egui::Ui,
+		palette: Palette,
+	) -> Option<PathBuf> {
+		let search = self.search.to_lowercase();
+		let mut picked = None;
+
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			for entry in self
+				.entries
+				.iter_mut()
+				.filter(|entry| entry.name.to_lowercase().contains(&search))
+			{
+				let response = ui
+					.horizontal(|ui| {
+						let size = egui::Vec2::new(
+							ch8_core::WIDTH as f32 * THUMBNAIL_SCALE,
+							ch8_core::HEIGHT as f32 * THUMBNAIL_SCALE,
+						);
+
+						let pending = matches!(entry.thumbnail, Thumbnail::Pending);
+						match entry.texture(ctx, palette) {
+							Some(texture) => {
+								ui.image(texture, size);
+							}
+							None if pending => {
+								ui.add_sized(size, egui::Spinner::new());
+							}
+							None => {
+								ui.add_sized(size, egui::Label::new("No preview"));
+							}
+						}
+
+						ui.vertical(|ui| {
+							ui.strong(entry.name.as_str());
+							ui.label(format!("{} bytes", entry.size));
+							ui.label(entry.kind.to_string());
+						});
+					})
+					.response
+					.interact(egui::Sense::click());
+
+				if response.double_clicked() {
+					picked = Some(entry.path.clone());
+				}
+
+				ui.separator();
+			}
+		});
+
+		picked
+	}
+}
@@ -1,13 +1,42 @@
 //Disable terminal window opening on windows machines when built in release mode
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cli;
+mod coverage_view;
+mod debugger;
+mod disassembly;
+mod gamepad;
 mod gui;
+mod hotkeys;
+mod keymap;
+mod keypad;
+mod library;
+mod memory_viewer;
+mod profiler_view;
+mod settings;
+mod sprite_viewer;
 
 use eframe::egui;
 
 fn main() {
 	env_logger::init();
 
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let file = match cli::parse(&args) {
+		Ok(cli::Command::Gui(file)) => file,
+		Ok(command) => {
+			if let Err(e) = cli::run(command) {
+				eprintln!("{}", e);
+				std::process::exit(1);
+			}
+			return;
+		}
+		Err(e) => {
+			eprintln!("{}\n\n{}", e, cli::USAGE);
+			std::process::exit(2);
+		}
+	};
+
 	let options = eframe::NativeOptions {
 		resizable: true,
 		min_window_size: Some(egui::Vec2::new(
@@ -22,6 +51,6 @@ fn main() {
 	eframe::run_native(
 		"Chip-8 Emulator",
 		options,
-		Box::new(move |cc| Box::new(gui::Gui::new(cc))),
+		Box::new(move |cc| Box::new(gui::Gui::new(cc, file))),
 	);
 }
@@ -0,0 +1,277 @@
+use ch8_core::{CoreState, FONT_ADDRESS, FONT_SIZE, ROM_ADDRESS};
+use eframe::egui;
+use egui::{Color32, RichText};
+use log::warn;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const BYTES_PER_ROW: usize = 16;
+const ROWS: usize = 4096 / BYTES_PER_ROW;
+///How long written bytes stay highlighted
+const FLASH_DURATION: Duration = Duration::from_millis(1000);
+
+const PC_COLOR: Color32 = Color32::from_rgb(0x30, 0x70, 0xC0);
+const I_COLOR: Color32 = Color32::from_rgb(0xC0, 0x80, 0x20);
+const FONT_COLOR: Color32 = Color32::from_rgb(0x50, 0xA0, 0x50);
+const ROM_COLOR: Color32 = Color32::from_rgb(0x70, 0x90, 0xE0);
+const WRITE_COLOR: Color32 = Color32::from_rgb(0xD0, 0x40, 0x40);
+
+///Hex and ASCII view of the CHIP-8 memory, editable while the core is paused
+#[derive(Default)]
+pub struct MemoryViewer {
+	pub open: bool,
+	goto: String,
+	///Row that is scrolled to in the next frame
+	scroll_to: Option<usize>,
+	///Address being edited and the hex bytes entered so far
+	editing: Option<(u16, String)>,
+	focus_editor: bool,
+	previous_memory: Option<[u8; 4096]>,
+	///When the bytes that changed recently were written
+	written: HashMap<usize, Instant>,
+}
+
+impl MemoryViewer {
+	///Returns the address and bytes to write if an edit was confirmed
+	pub fn show(&mut self, ctx: &egui::Context, state: &CoreState) -> Option<(u16, Vec<u8>)> {
+		if !self.open {
+			self.previous_memory = None;
+			self.written.clear();
+			return None;
+		}
+
+		self.update_written(&state.memory);
+		if !self.written.is_empty() {
+			ctx.request_repaint();
+		}
+
+		let editable = !state.running && state.error.is_none();
+		if !editable {
+			self.editing = None;
+		}
+
+		let mut open = self.open;
+		let mut write = None;
+
+		egui::Window::new("Memory")
+			.open(&mut open)
+			.default_size([560.0, 500.0])
+			.show(ctx, |ui| {
+				self.show_navigation(ui, state);
+				self.show_legend(ui);
+
+				if editable {
+					ui.label(
+						"Click a byte to edit it, enter one or more hex bytes and press enter",
+					);
+				} else {
+					ui.label("Pause to edit memory");
+				}
+
+				ui.separator();
+
+				let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+				let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false; 2]);
+				if let Some(row) = self.scroll_to.take() {
+					let offset = row as f32 * (row_height + ui.spacing().item_spacing.y);
+					scroll_area = scroll_area.vertical_scroll_offset(offset);
+				}
+
+				scroll_area.show_rows(ui, row_height, ROWS, |ui, rows| {
+					for row in rows {
+						if let Some(bytes) = self.show_row(ui, state, row, editable) {
+							write = Some(bytes);
+						}
+					}
+				});
+			});
+
+		self.open = open;
+
+		write
+	}
+
+	fn update_written(&mut self, memory: &[u8; 4096]) {
+		let now = Instant::now();
+
+		if let Some(previous) = &self.previous_memory {
+			for (address, (old, new)) in previous.iter().zip(memory).enumerate() {
+				if old != new {
+					self.written.insert(address, now);
+				}
+			}
+		}
+
+		self.previous_memory = Some(*memory);
+		self.written
+			.retain(|_, time| now.duration_since(*time) < FLASH_DURATION);
+	}
+
+	fn show_navigation(&mut self, ui: &mut egui::Ui, state: &CoreState) {
+		ui.horizontal(|ui| {
+			let response = ui.add(
+				egui::TextEdit::singleline(&mut self.goto)
+					.hint_text("Address (hex)")
+					.desired_width(100.0),
+			);
+			let entered = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+
+			if ui.button("Go").clicked() || entered {
+				let address = self.goto.trim().trim_start_matches("0x");
+
+				match usize::from_str_radix(address, 16) {
+					Ok(address) if address < 4096 => self.scroll_to = Some(address / BYTES_PER_ROW),
+					_ => warn!("Invalid memory address: {}", self.goto),
+				}
+			}
+
+			if ui
+				.button(format!("PC ({:03X})", state.program_counter))
+				.clicked()
+			{
+				self.scroll_to = Some(state.program_counter as usize / BYTES_PER_ROW);
+			}
+			if ui.button(format!("I ({:03X})", state.i_register)).clicked() {
+				self.scroll_to = Some(state.i_register as usize / BYTES_PER_ROW);
+			}
+		});
+	}
+
+	fn show_legend(&self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.label(RichText::new("PC").monospace().background_color(PC_COLOR));
+			ui.label(RichText::new("I").monospace().background_color(I_COLOR));
+			ui.label(RichText::new("Font").monospace().color(FONT_COLOR));
+			ui.label(RichText::new("ROM").monospace().color(ROM_COLOR));
+			ui.label(
+				RichText::new("Written")
+					.monospace()
+					.background_color(WRITE_COLOR),
+			);
+		});
+	}
+
+	fn show_row(
+		&mut self,
+		ui: &mut egui::Ui,
+		state: &CoreState,
+		row: usize,
+		editable: bool,
+	) -> Option<(u16, Vec<u8>)> {
+		let start = row * BYTES_PER_ROW;
+		let bytes = &state.memory[start..start + BYTES_PER_ROW];
+
+		ui.horizontal(|ui| {
+			let mut write = None;
+			ui.spacing_mut().item_spacing.x = 4.0;
+
+			ui.label(RichText::new(format!("{:03X}", start)).monospace().weak());
+			ui.add_space(8.0);
+
+			for address in start..start + BYTES_PER_ROW {
+				if matches!(&self.editing, Some((editing, _)) if *editing as usize == address) {
+					write = write.or(self.show_editor(ui));
+					continue;
+				}
+
+				let response = ui.add(
+					egui::Label::new(self.byte_text(state, address)).sense(egui::Sense::click()),
+				);
+
+				if editable && response.clicked() {
+					self.editing = Some((address as u16, format!("{:02X}", state.memory[address])));
+					self.focus_editor = true;
+				}
+			}
+
+			ui.add_space(8.0);
+
+			let ascii: String = bytes
+				.iter()
+				.map(|byte| {
+					if byte.is_ascii_graphic() || *byte == b' ' {
+						*byte as char
+					} else {
+						'.'
+					}
+				})
+				.collect();
+			ui.label(RichText::new(ascii).monospace());
+
+			write
+		})
+		.inner
+	}
+
+	fn show_editor(&mut self, ui: &mut egui::Ui) -> Option<(u16, Vec<u8>)> {
+		let (address, text) = self.editing.as_mut()?;
+		let address = *address;
+
+		let width = ui
+			.fonts()
+			.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), '0')
+			* 2.0;
+		let response = ui.add(
+			egui::TextEdit::singleline(text)
+				.font(egui::TextStyle::Monospace)
+				.desired_width(width)
+				.frame(false),
+		);
+
+		if std::mem::take(&mut self.focus_editor) {
+			response.request_focus();
+		}
+
+		if !response.lost_focus() {
+			return None;
+		}
+
+		let confirmed = ui.input().key_pressed(egui::Key::Enter);
+		let text = self.editing.take()?.1;
+		if !confirmed {
+			return None;
+		}
+
+		match text
+			.split_whitespace()
+			.map(|byte| u8::from_str_radix(byte, 16))
+			.collect::<Result<Vec<u8>, _>>()
+		{
+			Ok(bytes) if !bytes.is_empty() => Some((address, bytes)),
+			_ => {
+				warn!("Invalid hex bytes: {}", text);
+				None
+			}
+		}
+	}
+
+	fn byte_text(&self, state: &CoreState, address: usize) -> RichText {
+		let mut text = RichText::new(format!("{:02X}", state.memory[address])).monospace();
+
+		let rom_end = ROM_ADDRESS + state.rom_size.unwrap_or(0);
+		if (FONT_ADDRESS..FONT_ADDRESS + FONT_SIZE).contains(&address) {
+			text = text.color(FONT_COLOR);
+		} else if (ROM_ADDRESS..rom_end).contains(&address) {
+			text = text.color(ROM_COLOR);
+		}
+
+		let pc = state.program_counter as usize;
+		if address == pc || address == pc + 1 {
+			text = text.background_color(PC_COLOR);
+		} else if address == state.i_register as usize {
+			text = text.background_color(I_COLOR);
+		} else if let Some(time) = self.written.get(&address) {
+			//Fade out over the flash duration
+			let age = time.elapsed().as_secs_f32() / FLASH_DURATION.as_secs_f32();
+			let alpha = ((1.0 - age).max(0.0) * 255.0) as u8;
+			text = text.background_color(Color32::from_rgba_unmultiplied(
+				WRITE_COLOR.r(),
+				WRITE_COLOR.g(),
+				WRITE_COLOR.b(),
+				alpha,
+			));
+		}
+
+		text
+	}
+}
@@ -0,0 +1,178 @@
+use ch8_core::disassembler::Line;
+use ch8_core::profiler::{Profile, Wait};
+use ch8_core::CoreState;
+use eframe::egui;
+
+///Number of executed addresses listed
+const ADDRESSES: usize = 32;
+
+pub enum ProfilerAction {
+	Enable(bool),
+	Reset,
+	Export,
+}
+
+///Execution counts of the profiler, to find the code that uses most of the cycles
+pub struct ProfilerView {
+	pub open: bool,
+}
+
+impl ProfilerView {
+	pub fn new() -> Self {
+		Self { open: false }
+	}
+
+	pub fn show(&mut self, ctx: &egui::Context, state: &CoreState) -> Option<ProfilerAction> {
+		if !self.open {
+			return None;
+		}
+
+		let mut open = self.open;
+		let mut action = None;
+
+		egui::Window::new("Profiler")
+			.open(&mut open)
+			.default_width(360.0)
+			.show(ctx, |ui| {
+				ui.horizontal(|ui| {
+					let mut enabled = state.profile.is_some();
+					if ui.checkbox(&mut enabled, "Enabled").changed() {
+						action = Some(ProfilerAction::Enable(enabled));
+					}
+
+					ui.add_enabled_ui(state.profile.is_some(), |ui| {
+						if ui.button("Reset").clicked() {
+							action = Some(ProfilerAction::Reset);
+						}
+						if ui.button("Export CSV…").clicked() {
+							action = Some(ProfilerAction::Export);
+						}
+					});
+				});
+
+				match &state.profile {
+					Some(profile) => show_profile(ui, profile, &state.memory),
+					None => {
+						ui.weak("Counts the executed instructions while enabled");
+					}
+				}
+			});
+
+		self.open = open;
+
+		action
+	}
+}
+
+fn show_profile(ui: &mut egui::Ui, profile: &Profile, memory: &[u8; 4096]) {
+	ui.separator();
+
+	ui.label(format!(
+		"{} cycles, {} instructions executed",
+		profile.cycles(),
+		profile.instructions
+	));
+	egui::Grid::new("profiler_waits").show(ui, |ui| {
+		for (name, wait) in [
+			("DXYN VBlank wait", profile.vblank_wait),
+			("FX0A key wait", profile.key_wait),
+		] {
+			show_wait(ui, profile, name, wait);
+		}
+	});
+
+	ui.collapsing("Opcode classes", |ui| {
+		egui::Grid::new("profiler_classes")
+			.striped(true)
+			.show(ui, |ui| {
+				ui.strong("Class");
+				ui.strong("Instructions");
+				ui.strong("Share");
+				ui.end_row();
+
+				for (class, count) in profile.opcode_classes.iter().enumerate() {
+					ui.monospace(format!("{:X}___", class));
+					ui.monospace(count.to_string());
+					ui.monospace(format!("{:.1}%", profile.share(*count)));
+					ui.end_row();
+				}
+			});
+	});
+
+	egui::CollapsingHeader::new("Hottest routines")
+		.default_open(true)
+		.show(ui, |ui| {
+			egui::ScrollArea::vertical()
+				.id_source("profiler_routines")
+				.max_height(200.0)
+				.show(ui, |ui| {
+					egui::Grid::new("profiler_routines")
+						.striped(true)
+						.show(ui, |ui| {
+							ui.strong("Routine");
+							ui.strong("Calls");
+							ui.strong("Self");
+							ui.strong("Total");
+							ui.end_row();
+
+							for (target, routine) in profile.hottest_routines() {
+								let name = match target {
+									Some(target) => format!("{:03X}", target),
+									None => "main".into(),
+								};
+								ui.monospace(name);
+								ui.monospace(routine.calls.to_string());
+								ui.monospace(format!(
+									"{} ({:.1}%)",
+									routine.instructions,
+									profile.share(routine.instructions)
+								));
+								ui.monospace(format!(
+									"{} ({:.1}%)",
+									routine.inclusive_instructions,
+									profile.share(routine.inclusive_instructions)
+								));
+								ui.end_row();
+							}
+						});
+				});
+			ui.weak("Routines are grouped by 2NNN call target, total includes the called routines");
+		});
+
+	ui.collapsing("Hottest addresses", |ui| {
+		egui::ScrollArea::vertical()
+			.id_source("profiler_addresses")
+			.max_height(200.0)
+			.show(ui, |ui| {
+				egui::Grid::new("profiler_addresses")
+					.striped(true)
+					.show(ui, |ui| {
+						for (address, count) in
+							profile.hottest_addresses().into_iter().take(ADDRESSES)
+						{
+							let index = address as usize;
+							let opcode = u16::from_be_bytes([
+								memory[index],
+								memory[(index + 1) % memory.len()],
+							]);
+
+							ui.monospace(format!("{:03X}", address));
+							ui.monospace(Line::new(address, opcode).to_string());
+							ui.monospace(format!("{} ({:.1}%)", count, profile.share(count)));
+							ui.end_row();
+						}
+					});
+			});
+	});
+}
+
+fn show_wait(ui: &mut egui::Ui, profile: &Profile, name: &str, wait: Wait) {
+	ui.label(name);
+	ui.monospace(format!("{} frames", wait.frames));
+	ui.monospace(format!(
+		"{} cycles ({:.1}%)",
+		wait.cycles,
+		profile.share(wait.cycles)
+	));
+	ui.end_row();
+}
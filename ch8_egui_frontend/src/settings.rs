@@ -0,0 +1,189 @@
+use crate::gamepad::{GamepadMap, DEFAULT_GAMEPAD_MAP};
+use crate::gui::{ScaleMode, SideMenuSection, DEFAULT_SCALE};
+use crate::hotkeys::{Hotkey, HotkeyMap};
+use crate::keymap;
+use crate::keymap::{Keymap, BINDS_PER_KEY};
+use ch8_core::database;
+use ch8_core::display_filter::DisplayFilter;
+use ch8_core::palette::Palette;
+use ch8_core::quirks::Quirks;
+use ch8_core::upscale::Upscaler;
+use eframe::egui;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::path::PathBuf;
+use std::{fmt, fs};
+
+///Settings that are kept between launches through the eframe storage
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+	///`None` follows the system theme
+	pub dark_theme: Option<bool>,
+	///`None` picks a scale based on the monitor size
+	pub scale: Option<f32>,
+	pub scale_mode: ScaleMode,
+	pub opcodes_per_frame: u32,
+	pub quirks: Quirks,
+	///First binding of every CHIP-8 key
+	pub keymap: [Option<SavedKeyBind>; 16],
+	pub secondary_keymap: [Option<SavedKeyBind>; 16],
+	pub gamepad_map: GamepadMap,
+	pub hotkeys: HashMap<Hotkey, Option<SavedKeyBind>>,
+	pub side_menu_sections: Vec<SideMenuSection>,
+	pub side_menu_visible: bool,
+	pub palette: Palette,
+	///Settings that override the global ones for single ROMs, keyed by ROM hash
+	pub rom_profiles: HashMap<String, RomProfile>,
+	///Palettes of single ROMs keyed by path from older versions, moved to `rom_profiles` on load
+	#[serde(skip_serializing)]
+	pub rom_palettes: HashMap<PathBuf, Palette>,
+	pub display_filter: DisplayFilter,
+	pub upscaler: Upscaler,
+	pub screenshot_scale: usize,
+	pub screenshot_directory: Option<PathBuf>,
+	pub last_rom_path: Option<PathBuf>,
+	pub resume_last_rom: bool,
+	pub library_directories: Vec<PathBuf>,
+	pub recent_roms: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			dark_theme: None,
+			scale: None,
+			scale_mode: ScaleMode::Fit,
+			opcodes_per_frame: 20,
+			quirks: Quirks::default(),
+			keymap: keymap::to_saved(&keymap::default_keymap()).map(|binds| binds[0]),
+			secondary_keymap: [None; 16],
+			gamepad_map: DEFAULT_GAMEPAD_MAP,
+			hotkeys: HotkeyMap::default().to_saved(),
+			side_menu_sections: SideMenuSection::ALL.to_vec(),
+			side_menu_visible: true,
+			palette: Palette::default(),
+			rom_profiles: HashMap::new(),
+			rom_palettes: HashMap::new(),
+			display_filter: DisplayFilter::None,
+			upscaler: Upscaler::None,
+			screenshot_scale: DEFAULT_SCALE as usize,
+			screenshot_directory: None,
+			last_rom_path: None,
+			resume_last_rom: false,
+			library_directories: vec![],
+			recent_roms: vec![],
+		}
+	}
+}
+
+impl Settings {
+	///Move the palettes of older versions to the profiles of the ROMs, which are keyed by hash.
+	///Palettes of ROMs that cannot be read anymore are dropped.
+	pub fn migrate(&mut self) {
+		for (path, palette) in self.rom_palettes.drain() {
+			let rom = match fs::read(&path) {
+				Ok(rom) => rom,
+				Err(e) => {
+					warn!(
+						"Dropping the stored palette of {}, the ROM cannot be read: {}",
+						path.display(),
+						e
+					);
+					continue;
+				}
+			};
+
+			let profile = self.rom_profiles.entry(database::hash(&rom)).or_default();
+			if profile.palette.is_none() {
+				profile.palette = Some(palette);
+			}
+		}
+	}
+
+	pub fn keymap(&self) -> Keymap {
+		let mut keymap: SavedKeymap = [[None; BINDS_PER_KEY]; 16];
+		for (key, binds) in keymap.iter_mut().enumerate() {
+			*binds = [self.keymap[key], self.secondary_keymap[key]];
+		}
+
+		keymap::from_saved(&keymap)
+	}
+
+	///Stored side menu order, sections added since the settings were saved are appended
+	pub fn side_menu_sections(&self) -> Vec<SideMenuSection> {
+		let mut sections = vec![];
+
+		for section in self
+			.side_menu_sections
+			.iter()
+			.chain(SideMenuSection::ALL.iter())
+		{
+			if !sections.contains(section) {
+				sections.push(*section);
+			}
+		}
+
+		sections
+	}
+}
+
+///Settings of a single ROM, `None` uses the ROM database entry or the global setting
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomProfile {
+	pub opcodes_per_frame: Option<u32>,
+	pub quirks: Option<Quirks>,
+	pub keymap: Option<SavedKeymap>,
+	pub gamepad_map: Option<GamepadMap>,
+	pub palette: Option<Palette>,
+}
+
+impl RomProfile {
+	pub fn is_empty(&self) -> bool {
+		self.opcodes_per_frame.is_none()
+			&& self.quirks.is_none()
+			&& self.keymap.is_none()
+			&& self.gamepad_map.is_none()
+			&& self.palette.is_none()
+	}
+}
+
+pub type SavedKeyBind = (SavedBind, egui::Modifiers);
+pub type SavedKeymap = [[Option<SavedKeyBind>; BINDS_PER_KEY]; 16];
+
+///Serializable version of [`egui_bind::KeyOrPointer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SavedBind {
+	Key(egui::Key),
+	Pointer(egui::PointerButton),
+}
+
+impl From<egui_bind::KeyOrPointer> for SavedBind {
+	fn from(bind: egui_bind::KeyOrPointer) -> Self {
+		match bind {
+			egui_bind::KeyOrPointer::Key(key) => SavedBind::Key(key),
+			egui_bind::KeyOrPointer::Pointer(button) => SavedBind::Pointer(button),
+		}
+	}
+}
+
+impl From<SavedBind> for egui_bind::KeyOrPointer {
+	fn from(bind: SavedBind) -> Self {
+		match bind {
+			SavedBind::Key(key) => egui_bind::KeyOrPointer::Key(key),
+			SavedBind::Pointer(button) => egui_bind::KeyOrPointer::Pointer(button),
+		}
+	}
+}
+
+impl fmt::Display for SavedBind {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		match self {
+			SavedBind::Key(key) => write!(f, "{:?}", key),
+			SavedBind::Pointer(button) => write!(f, "Mouse {:?}", button),
+		}
+	}
+}
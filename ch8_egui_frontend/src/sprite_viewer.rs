@@ -0,0 +1,233 @@
+use ch8_core::palette::Palette;
+use ch8_core::{CoreState, FONT_ADDRESS};
+use eframe::egui;
+use egui::Color32;
+use log::warn;
+
+const PIXEL_SIZE: f32 = 16.0;
+const FONT_PIXEL_SIZE: f32 = 4.0;
+///Bytes of a character of the built-in font
+const FONT_CHARACTER_SIZE: usize = 5;
+
+///Sprite formats DXYN can draw
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SpriteMode {
+	///8 pixels wide, N rows
+	Chip8,
+	///16x16 pixels, drawn by DXY0 in SUPER-CHIP
+	SuperChip,
+}
+
+///Renders memory as a sprite, to check what DXYN draws
+pub struct SpriteViewer {
+	pub open: bool,
+	///Show the sprite at I instead of `address`
+	follow_i: bool,
+	address: u16,
+	rows: u8,
+	mode: SpriteMode,
+}
+
+impl SpriteViewer {
+	pub fn new() -> Self {
+		Self {
+			open: false,
+			follow_i: true,
+			address: ch8_core::ROM_ADDRESS as u16,
+			rows: 15,
+			mode: SpriteMode::Chip8,
+		}
+	}
+
+	///Returns the address and new value of a byte if a pixel was toggled
+	pub fn show(
+		&mut self,
+		ctx: &egui::Context,
+		state: &CoreState,
+		palette: Palette,
+	) -> Option<(u16, u8)> {
+		if !self.open {
+			return None;
+		}
+
+		let editable = !state.running && state.error.is_none();
+		let mut open = self.open;
+		let mut write = None;
+
+		egui::Window::new("Sprite").open(&mut open).show(ctx, |ui| {
+			self.show_options(ui);
+
+			if self.follow_i {
+				self.address = state.i_register;
+			}
+
+			let (bytes_per_row, rows) = match self.mode {
+				SpriteMode::Chip8 => (1, self.rows as usize),
+				SpriteMode::SuperChip => (2, 16),
+			};
+
+			ui.label(format!(
+				"{} bytes at {:03X}",
+				bytes_per_row * rows,
+				self.address
+			));
+
+			write = show_sprite(
+				ui,
+				&state.memory,
+				self.address as usize,
+				bytes_per_row,
+				rows,
+				PIXEL_SIZE,
+				palette,
+				editable,
+			);
+
+			if editable {
+				ui.label("Click pixels to toggle them");
+			} else {
+				ui.label("Pause to edit the sprite");
+			}
+
+			ui.separator();
+
+			if let Some(address) = self.show_font(ui, state, palette) {
+				self.follow_i = false;
+				self.address = address;
+			}
+		});
+
+		self.open = open;
+
+		write
+	}
+
+	fn show_options(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			ui.checkbox(&mut self.follow_i, "Follow I");
+
+			ui.add_enabled_ui(!self.follow_i, |ui| {
+				ui.add(
+					egui::DragValue::new(&mut self.address)
+						.clamp_range(0..=0xFFF)
+						.custom_formatter(|address, _| format!("{:03X}", address as u16)),
+				)
+				.on_hover_text("Address");
+			});
+		});
+
+		ui.horizontal(|ui| {
+			ui.radio_value(&mut self.mode, SpriteMode::Chip8, "8xN");
+			ui.radio_value(&mut self.mode, SpriteMode::SuperChip, "16x16 (SUPER-CHIP)");
+
+			ui.add_enabled_ui(self.mode == SpriteMode::Chip8, |ui| {
+				ui.add(
+					egui::DragValue::new(&mut self.rows)
+						.clamp_range(1..=15)
+						.prefix("N: "),
+				);
+			});
+		});
+	}
+
+	///Show the built-in font. Returns the address of a character that was clicked.
+	fn show_font(&self, ui: &mut egui::Ui, state: &CoreState, palette: Palette) -> Option<u16> {
+		let mut clicked = None;
+
+		ui.label("Font");
+		ui.horizontal_wrapped(|ui| {
+			for character in 0..16 {
+				let address = FONT_ADDRESS + character * FONT_CHARACTER_SIZE;
+
+				let response = ui
+					.vertical(|ui| {
+						ui.label(format!("{:X}", character));
+						show_sprite(
+							ui,
+							&state.memory,
+							address,
+							1,
+							FONT_CHARACTER_SIZE,
+							FONT_PIXEL_SIZE,
+							palette,
+							false,
+						);
+					})
+					.response
+					.interact(egui::Sense::click());
+
+				if response.on_hover_text(format!("{:03X}", address)).clicked() {
+					clicked = Some(address as u16);
+				}
+			}
+		});
+
+		clicked
+	}
+}
+
+///Paint `rows` rows of `bytes_per_row` bytes starting at `address` as pixels.
+///If `editable`, returns the address and new value of the byte whose pixel was clicked.
+#[allow(clippy::too_many_arguments)]
+fn show_sprite(
+	ui: &mut egui::Ui,
+	memory: &[u8; 4096],
+	address: usize,
+	bytes_per_row: usize,
+	rows: usize,
+	pixel_size: f32,
+	palette: Palette,
+	editable: bool,
+) -> Option<(u16, u8)> {
+	let [r, g, b] = palette.background;
+	let background = Color32::from_rgb(r, g, b);
+	let [r, g, b] = palette.foreground;
+	let foreground = Color32::from_rgb(r, g, b);
+
+	let width = bytes_per_row * 8;
+	let size = egui::Vec2::new(width as f32, rows as f32) * pixel_size;
+	let sense = if editable {
+		egui::Sense::click()
+	} else {
+		egui::Sense::hover()
+	};
+	let (rect, response) = ui.allocate_exact_size(size, sense);
+
+	let painter = ui.painter_at(rect);
+	painter.rect_filled(rect, 0.0, background);
+
+	//Wrap around the end of the memory instead of reading past it
+	let byte_address = |row: usize, col: usize| (address + row * bytes_per_row + col / 8) % 4096;
+
+	for row in 0..rows {
+		for col in 0..width {
+			let byte = memory[byte_address(row, col)];
+			if byte & (0x80 >> (col % 8)) == 0 {
+				continue;
+			}
+
+			let min = rect.min + egui::Vec2::new(col as f32, row as f32) * pixel_size;
+			painter.rect_filled(
+				egui::Rect::from_min_size(min, egui::Vec2::splat(pixel_size)),
+				0.0,
+				foreground,
+			);
+		}
+	}
+
+	if !response.clicked() {
+		return None;
+	}
+
+	let position = response.interact_pointer_pos()? - rect.min;
+	let col = (position.x / pixel_size) as usize;
+	let row = (position.y / pixel_size) as usize;
+	if col >= width || row >= rows {
+		warn!("Clicked outside of the sprite");
+		return None;
+	}
+
+	let byte_address = byte_address(row, col);
+	let value = memory[byte_address] ^ (0x80 >> (col % 8));
+	Some((byte_address as u16, value))
+}